@@ -0,0 +1,285 @@
+//! ZIP archives of dropped files and folders, built on the fly.
+//!
+//! The archive is written on a blocking thread and handed to the uploader as a byte
+//! stream, so nothing is staged on disk. A failure while building the archive is
+//! delivered as a read error, which keeps a truncated archive from being completed.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Datelike, Local, Timelike};
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncRead;
+use tokio::sync::mpsc;
+use tokio_util::bytes::Bytes;
+use tokio_util::io::StreamReader;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::folder;
+
+/// Archive bytes buffered before they are handed to the uploader.
+const WRITE_BUFFER_SIZE: usize = 256 * 1024;
+/// Buffers queued between the archive thread and the uploader.
+const CHANNEL_DEPTH: usize = 16;
+
+/// Extensions of formats that are already compressed and are stored as-is.
+const STORED_EXTENSIONS: [&str; 14] = [
+    "jpg", "jpeg", "png", "gif", "webp", "mp4", "mkv", "mov", "mp3", "zip", "7z", "rar", "gz",
+    "zst",
+];
+
+/// File to be added to an archive.
+#[derive(Debug, Clone)]
+pub struct ArchiveEntry {
+    pub path: PathBuf,
+    /// Path inside the archive, `/`-separated
+    pub name: String,
+    pub size: u64,
+}
+
+/// Archive entries for dropped `paths`. Folders keep their structure under their own name,
+/// using the same filtering as folder uploads.
+pub fn collect_entries(paths: &[PathBuf]) -> Result<Vec<ArchiveEntry>> {
+    let mut entries = Vec::new();
+    let mut used_names = HashSet::new();
+
+    for path in paths {
+        let base_name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "file".to_string());
+
+        let files = if path.is_dir() {
+            folder::collect_files(path)?
+                .into_iter()
+                .map(|f| (f.path, format!("{base_name}/{}", f.relative)))
+                .collect()
+        } else {
+            vec![(path.clone(), base_name)]
+        };
+
+        for (file_path, name) in files {
+            let size = fs::metadata(&file_path)
+                .with_context(|| format!("Failed to get file metadata: {}", file_path.display()))?
+                .len();
+            entries.push(ArchiveEntry {
+                path: file_path,
+                name: unique_name(&mut used_names, name),
+                size,
+            });
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Archive file name for dropped `paths`: the folder or file name for a single item,
+/// otherwise the name of the directory they were dropped from.
+pub fn archive_name(paths: &[PathBuf]) -> String {
+    let stem = match paths {
+        [single] if single.is_dir() => single.file_name(),
+        [single] => single.file_stem(),
+        [first, rest @ ..] => first
+            .parent()
+            .filter(|parent| rest.iter().all(|p| p.parent() == Some(*parent)))
+            .and_then(Path::file_name),
+        [] => None,
+    };
+
+    match stem {
+        Some(stem) => format!("{}.zip", stem.to_string_lossy()),
+        None => "archive.zip".to_string(),
+    }
+}
+
+/// Stream a ZIP of `entries`. Reading fails if any file cannot be archived.
+pub fn zip_stream(entries: Vec<ArchiveEntry>) -> impl AsyncRead + Unpin {
+    let (tx, mut rx) = mpsc::channel::<io::Result<Bytes>>(CHANNEL_DEPTH);
+
+    tokio::task::spawn_blocking(move || {
+        let writer = BufWriter::with_capacity(WRITE_BUFFER_SIZE, ChannelWriter { tx: tx.clone() });
+        if let Err(e) = write_zip(&entries, writer) {
+            tracing::warn!("Failed to build archive: {:#}", e);
+            let _ = tx.blocking_send(Err(io::Error::other(format!("{e:#}"))));
+        }
+    });
+
+    StreamReader::new(futures::stream::poll_fn(move |cx| rx.poll_recv(cx)))
+}
+
+fn write_zip<W: Write>(entries: &[ArchiveEntry], out: W) -> Result<W> {
+    let mut zip = ZipWriter::new_stream(out);
+
+    for entry in entries {
+        let mut file = fs::File::open(&entry.path)
+            .with_context(|| format!("Failed to open file: {}", entry.path.display()))?;
+
+        let mut options = SimpleFileOptions::default()
+            .compression_method(compression_for(&entry.name))
+            .large_file(entry.size >= u64::from(u32::MAX));
+        if let Some(modified) = file
+            .metadata()
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| zip_time(t.into()))
+        {
+            options = options.last_modified_time(modified);
+        }
+
+        zip.start_file(entry.name.as_str(), options)
+            .with_context(|| format!("Failed to add {} to archive", entry.name))?;
+        io::copy(&mut file, &mut zip)
+            .with_context(|| format!("Failed to archive file: {}", entry.path.display()))?;
+    }
+
+    let mut out = zip.finish().context("Failed to finish archive")?.into_inner();
+    out.flush().context("Failed to write archive")?;
+    Ok(out)
+}
+
+fn compression_for(name: &str) -> CompressionMethod {
+    let compressed = Path::new(name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| STORED_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()));
+
+    if compressed {
+        CompressionMethod::Stored
+    } else {
+        CompressionMethod::Deflated
+    }
+}
+
+/// ZIP timestamps are local time without a zone, limited to 1980..=2107.
+fn zip_time(time: DateTime<Local>) -> Option<zip::DateTime> {
+    zip::DateTime::from_date_and_time(
+        u16::try_from(time.year()).ok()?,
+        time.month() as u8,
+        time.day() as u8,
+        time.hour() as u8,
+        time.minute() as u8,
+        time.second() as u8,
+    )
+    .ok()
+}
+
+/// Make `name` unique within an archive by numbering repeats: `a.txt`, `a-2.txt`, ...
+fn unique_name(used: &mut HashSet<String>, name: String) -> String {
+    if used.insert(name.clone()) {
+        return name;
+    }
+
+    let (stem, ext) = match name.rfind('.') {
+        Some(pos) if !name[pos..].contains('/') => name.split_at(pos),
+        _ => (name.as_str(), ""),
+    };
+    let mut n = 2;
+    loop {
+        let candidate = format!("{stem}-{n}{ext}");
+        if used.insert(candidate.clone()) {
+            return candidate;
+        }
+        n += 1;
+    }
+}
+
+/// Blocking writer feeding the async side of the archive stream.
+struct ChannelWriter {
+    tx: mpsc::Sender<io::Result<Bytes>>,
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.tx
+            .blocking_send(Ok(Bytes::copy_from_slice(buf)))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "Archive upload stopped"))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Read};
+    use tempfile::TempDir;
+    use tokio::io::AsyncReadExt;
+
+    #[test]
+    fn test_archive_name() {
+        let temp_dir = TempDir::new().unwrap();
+        let base = temp_dir.path().join("Projekt");
+        fs::create_dir_all(base.join("docs")).unwrap();
+
+        assert_eq!(archive_name(&[base.join("docs")]), "docs.zip");
+        assert_eq!(archive_name(&[base.join("raport.pdf")]), "raport.zip");
+        assert_eq!(
+            archive_name(&[base.join("a.pdf"), base.join("b.pdf")]),
+            "Projekt.zip"
+        );
+        assert_eq!(
+            archive_name(&[base.join("a.pdf"), temp_dir.path().join("b.pdf")]),
+            "archive.zip"
+        );
+    }
+
+    #[test]
+    fn test_unique_name() {
+        let mut used = HashSet::new();
+
+        assert_eq!(unique_name(&mut used, "a.txt".to_string()), "a.txt");
+        assert_eq!(unique_name(&mut used, "a.txt".to_string()), "a-2.txt");
+        assert_eq!(unique_name(&mut used, "a.txt".to_string()), "a-3.txt");
+        assert_eq!(unique_name(&mut used, "dir.v2/b".to_string()), "dir.v2/b");
+        assert_eq!(unique_name(&mut used, "dir.v2/b".to_string()), "dir.v2/b-2");
+    }
+
+    #[tokio::test]
+    async fn test_zip_stream_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let base = temp_dir.path();
+        fs::create_dir_all(base.join("folder/sub")).unwrap();
+        fs::write(base.join("folder/sub/a.txt"), b"hello").unwrap();
+        fs::write(base.join("photo.jpg"), vec![7u8; 1024]).unwrap();
+
+        let entries = collect_entries(&[base.join("folder"), base.join("photo.jpg")]).unwrap();
+        let mut archive = Vec::new();
+        zip_stream(entries)
+            .read_to_end(&mut archive)
+            .await
+            .unwrap();
+
+        let mut zip = zip::ZipArchive::new(Cursor::new(archive)).unwrap();
+        assert_eq!(zip.len(), 2);
+
+        let mut content = String::new();
+        zip.by_name("folder/sub/a.txt")
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, "hello");
+
+        let photo = zip.by_name("photo.jpg").unwrap();
+        assert_eq!(photo.compression(), CompressionMethod::Stored);
+        assert_eq!(photo.size(), 1024);
+    }
+
+    #[tokio::test]
+    async fn test_zip_stream_reports_missing_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let entries = vec![ArchiveEntry {
+            path: temp_dir.path().join("missing.txt"),
+            name: "missing.txt".to_string(),
+            size: 0,
+        }];
+
+        let mut archive = Vec::new();
+        let result = zip_stream(entries).read_to_end(&mut archive).await;
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,208 @@
+//! Adaptive concurrency for file uploads and multipart parts.
+//!
+//! A limit starts at the configured value and is re-evaluated once per window
+//! of finished requests: a high error rate halves it, throughput that keeps
+//! rising raises it by one up to the admin ceiling, and throughput that drops
+//! backs it off by one. A fixed limit never moves.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Shortest period a throughput sample is taken over
+const WINDOW: Duration = Duration::from_secs(5);
+/// Finished requests needed before a window is evaluated
+const MIN_SAMPLES: u32 = 3;
+/// Share of failed requests in a window that halves the limit
+const MAX_ERROR_RATE: f64 = 0.2;
+/// Throughput change between windows that is still treated as unchanged
+const TOLERANCE: f64 = 0.1;
+
+pub struct AdaptiveLimit {
+    name: &'static str,
+    ceiling: usize,
+    adaptive: bool,
+    limit: AtomicUsize,
+    window: Mutex<Window>,
+}
+
+struct Window {
+    started: Instant,
+    bytes: u64,
+    successes: u32,
+    failures: u32,
+    /// Bytes per second of the last evaluated window
+    previous_throughput: Option<f64>,
+}
+
+impl Window {
+    fn new(started: Instant, previous_throughput: Option<f64>) -> Self {
+        Self {
+            started,
+            bytes: 0,
+            successes: 0,
+            failures: 0,
+            previous_throughput,
+        }
+    }
+}
+
+impl AdaptiveLimit {
+    /// Limit starting at `initial`. With `adaptive` it moves between 1 and `ceiling`,
+    /// otherwise it stays at `initial`.
+    pub fn new(name: &'static str, initial: u32, ceiling: u32, adaptive: bool) -> Self {
+        let initial = initial.max(1) as usize;
+        let ceiling = if adaptive {
+            ceiling.max(1) as usize
+        } else {
+            initial
+        };
+        Self {
+            name,
+            ceiling,
+            adaptive,
+            limit: AtomicUsize::new(initial.min(ceiling)),
+            window: Mutex::new(Window::new(Instant::now(), None)),
+        }
+    }
+
+    /// Current number of concurrent requests allowed.
+    pub fn limit(&self) -> usize {
+        self.limit.load(Ordering::Relaxed)
+    }
+
+    /// Highest value the limit can reach.
+    pub fn ceiling(&self) -> usize {
+        self.ceiling
+    }
+
+    /// Report a finished request. Only transient failures should be reported as
+    /// failed; errors such as denied access say nothing about the link.
+    pub fn record(&self, bytes: u64, succeeded: bool) {
+        self.record_at(bytes, succeeded, Instant::now());
+    }
+
+    fn record_at(&self, bytes: u64, succeeded: bool, now: Instant) {
+        if !self.adaptive {
+            return;
+        }
+        let Ok(mut window) = self.window.lock() else {
+            return;
+        };
+        if succeeded {
+            window.successes += 1;
+            window.bytes += bytes;
+        } else {
+            window.failures += 1;
+        }
+
+        let elapsed = now.saturating_duration_since(window.started);
+        let samples = window.successes + window.failures;
+        if elapsed < WINDOW || samples < MIN_SAMPLES {
+            return;
+        }
+
+        let throughput = window.bytes as f64 / elapsed.as_secs_f64();
+        let error_rate = f64::from(window.failures) / f64::from(samples);
+        let current = self.limit();
+        let next = adjust(
+            current,
+            self.ceiling,
+            throughput,
+            window.previous_throughput,
+            error_rate,
+        );
+        // A window full of errors says nothing about throughput at the new limit
+        let baseline = (error_rate <= MAX_ERROR_RATE).then_some(throughput);
+        *window = Window::new(now, baseline);
+        drop(window);
+
+        if next != current {
+            tracing::info!(
+                throughput_mbps = throughput * 8.0 / 1_000_000.0,
+                error_rate,
+                "{} concurrency {} -> {}",
+                self.name,
+                current,
+                next
+            );
+            self.limit.store(next, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Next limit after a window with `throughput` and `error_rate`.
+fn adjust(
+    current: usize,
+    ceiling: usize,
+    throughput: f64,
+    previous: Option<f64>,
+    error_rate: f64,
+) -> usize {
+    if error_rate > MAX_ERROR_RATE {
+        return (current / 2).max(1);
+    }
+    match previous {
+        Some(previous) if throughput < previous * (1.0 - TOLERANCE) => (current - 1).max(1),
+        Some(previous) if throughput <= previous * (1.0 + TOLERANCE) => current,
+        _ => (current + 1).min(ceiling),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adjust() {
+        // No baseline yet or rising throughput probes one step higher
+        assert_eq!(adjust(2, 8, 1000.0, None, 0.0), 3);
+        assert_eq!(adjust(3, 8, 1500.0, Some(1000.0), 0.0), 4);
+        assert_eq!(adjust(8, 8, 1500.0, Some(1000.0), 0.0), 8);
+        // A plateau holds, a drop backs off
+        assert_eq!(adjust(4, 8, 1050.0, Some(1000.0), 0.0), 4);
+        assert_eq!(adjust(4, 8, 700.0, Some(1000.0), 0.0), 3);
+        // Errors halve the limit, never below one
+        assert_eq!(adjust(6, 8, 5000.0, Some(1000.0), 0.5), 3);
+        assert_eq!(adjust(1, 8, 0.0, None, 1.0), 1);
+    }
+
+    #[test]
+    fn test_limit_reacts_to_windows() {
+        let limit = AdaptiveLimit::new("test", 2, 4, true);
+        let start = Instant::now();
+
+        for _ in 0..3 {
+            limit.record_at(1_000_000, true, start);
+        }
+        assert_eq!(limit.limit(), 2, "window not over yet");
+
+        limit.record_at(1_000_000, true, start + WINDOW);
+        assert_eq!(limit.limit(), 3);
+
+        let next = start + WINDOW * 2;
+        for _ in 0..3 {
+            limit.record_at(0, false, next);
+        }
+        assert_eq!(limit.limit(), 1);
+    }
+
+    #[test]
+    fn test_fixed_limit_never_moves() {
+        let limit = AdaptiveLimit::new("test", 3, 8, false);
+        let start = Instant::now();
+        for _ in 0..5 {
+            limit.record_at(0, false, start + WINDOW);
+        }
+
+        assert_eq!(limit.limit(), 3);
+        assert_eq!(limit.ceiling(), 3);
+    }
+
+    #[test]
+    fn test_initial_limit_capped_by_ceiling() {
+        let limit = AdaptiveLimit::new("test", 6, 4, true);
+        assert_eq!(limit.limit(), 4);
+        assert_eq!(limit.ceiling(), 4);
+    }
+}
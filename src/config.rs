@@ -1,34 +1,198 @@
 use crate::portable_crypto::EncryptedCredentials;
 use anyhow::{Context, Result};
+use chrono::{Datelike, NaiveDateTime, NaiveTime, Weekday};
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 /// Main configuration structure matching spec section 5.3
 #[derive(Deserialize, Serialize, Clone)]
 pub struct Config {
+    #[serde(default)]
+    pub storage: StorageConfig,
+    /// Bucket and endpoint of the S3-compatible backends
+    #[serde(default)]
     pub oracle: OracleConfig,
     pub app: AppConfig,
     pub advanced: AdvancedConfig,
     #[serde(default)]
+    pub bandwidth: BandwidthConfig,
+    #[serde(default)]
+    pub deferred: DeferredConfig,
+    #[serde(default)]
     pub credentials: Option<EncryptedCredentials>,
+    #[serde(default)]
+    pub oci_api: Option<OciApiConfig>,
 }
 
 impl std::fmt::Debug for Config {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Config")
+            .field("storage", &self.storage)
             .field("oracle", &self.oracle)
             .field("app", &self.app)
             .field("advanced", &self.advanced)
+            .field("bandwidth", &self.bandwidth)
+            .field("deferred", &self.deferred)
             .field(
                 "credentials",
                 &self.credentials.as_ref().map(|_| "[ENCRYPTED]"),
             )
+            .field("oci_api", &self.oci_api)
             .finish()
     }
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+/// Where uploaded files are stored.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct StorageConfig {
+    #[serde(default)]
+    pub backend: StorageBackend,
+    /// Provider behind the `s3` backend, sets addressing style, region and links
+    #[serde(default)]
+    pub provider: S3Provider,
+    /// Directory the local backend writes to
+    #[serde(default)]
+    pub local_path: Option<PathBuf>,
+    /// Link prefix for the local backend, e.g. a web server serving `local_path`;
+    /// without it links are `file://` URLs
+    #[serde(default)]
+    pub local_base_url: Option<String>,
+    /// Template of the link handed out, e.g. `https://files.example.pl/{key}` behind
+    /// a CDN; `{key}` and `{bucket}` are filled in, without `{key}` it is a prefix
+    #[serde(default)]
+    pub public_url: Option<String>,
+    /// Template of the direct download link, if it differs from the storage URL
+    #[serde(default)]
+    pub direct_url: Option<String>,
+    /// Keep the bucket private and hand out presigned links that expire; the URL
+    /// templates do not apply to them
+    #[serde(default)]
+    pub presigned_links: bool,
+    /// Hand out OCI Pre-Authenticated Request links that expire and can be revoked;
+    /// needs `[oci_api]`
+    #[serde(default)]
+    pub par_links: bool,
+    /// Validity of presigned and PAR links until another one is picked in the window
+    #[serde(default)]
+    pub link_expiry: LinkExpiry,
+}
+
+/// Validity of an expiring link.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum LinkExpiry {
+    #[serde(rename = "1h")]
+    Hour,
+    #[default]
+    #[serde(rename = "1d")]
+    Day,
+    #[serde(rename = "7d")]
+    Week,
+    #[serde(rename = "30d")]
+    Month,
+    #[serde(rename = "1y")]
+    Year,
+}
+
+impl LinkExpiry {
+    pub const ALL: [LinkExpiry; 5] = [
+        LinkExpiry::Hour,
+        LinkExpiry::Day,
+        LinkExpiry::Week,
+        LinkExpiry::Month,
+        LinkExpiry::Year,
+    ];
+
+    pub fn duration(self) -> std::time::Duration {
+        let hours = match self {
+            LinkExpiry::Hour => 1,
+            LinkExpiry::Day => 24,
+            LinkExpiry::Week => 7 * 24,
+            LinkExpiry::Month => 30 * 24,
+            LinkExpiry::Year => 365 * 24,
+        };
+        std::time::Duration::from_secs(hours * 3600)
+    }
+
+    /// SigV4 presigned links are valid for at most 7 days; PARs have no such limit.
+    pub fn presignable(self) -> bool {
+        self <= LinkExpiry::Week
+    }
+}
+
+/// OCI API signing key, used for the native API calls that manage PARs. These
+/// do not accept the S3 Customer Secret Keys.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
+pub struct OciApiConfig {
+    /// Tenancy OCID
+    pub tenancy: String,
+    /// OCID of the user the key belongs to
+    pub user: String,
+    pub fingerprint: String,
+    /// PEM private key; relative paths start at the app directory
+    pub key_file: PathBuf,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    /// Oracle Cloud Object Storage, with native OCI links
+    #[default]
+    Oci,
+    /// Any S3-compatible endpoint, see `S3Provider`
+    S3,
+    /// Files in a local or network directory
+    Local,
+}
+
+/// S3 provider of the `s3` backend.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum S3Provider {
+    /// Unknown S3-compatible service, path-style with `ENDPOINT/BUCKET/KEY` links
+    #[default]
+    Generic,
+    Minio,
+    Aws,
+    /// Cloudflare R2; the S3 API is never public, links need a public bucket domain
+    R2,
+    Backblaze,
+    Wasabi,
+}
+
+impl S3Provider {
+    /// Whether the bucket goes into the path rather than the host name.
+    pub fn path_style(self) -> bool {
+        matches!(
+            self,
+            S3Provider::Generic | S3Provider::Minio | S3Provider::R2
+        )
+    }
+
+    /// Region used when none is configured; `None` if the provider needs one.
+    fn default_region(self) -> Option<&'static str> {
+        match self {
+            S3Provider::Generic | S3Provider::Minio => Some("us-east-1"),
+            S3Provider::R2 => Some("auto"),
+            S3Provider::Aws | S3Provider::Backblaze | S3Provider::Wasabi => None,
+        }
+    }
+
+    /// Endpoint of `region`, for providers whose endpoints follow from the region.
+    fn regional_endpoint(self, region: &str) -> Option<String> {
+        match self {
+            S3Provider::Aws => Some(format!("https://s3.{region}.amazonaws.com")),
+            S3Provider::Backblaze => Some(format!("https://s3.{region}.backblazeb2.com")),
+            S3Provider::Wasabi => Some(format!("https://s3.{region}.wasabisys.com")),
+            S3Provider::Generic | S3Provider::Minio | S3Provider::R2 => None,
+        }
+    }
+}
+
+/// Fields a provider can derive may be left out.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
 pub struct OracleConfig {
     pub endpoint: String,
     pub bucket: String,
@@ -44,6 +208,31 @@ pub struct AppConfig {
     pub window_x: Option<f32>,
     #[serde(default)]
     pub window_y: Option<f32>,
+    /// Return the existing link when identical content was already uploaded
+    #[serde(default)]
+    pub reuse_existing_links: bool,
+    /// How multiple dropped files or folders are sent; Shift while dropping picks the other mode
+    #[serde(default)]
+    pub drop_mode: DropMode,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DropMode {
+    /// Every file is uploaded separately and gets its own link
+    #[default]
+    Files,
+    /// Dropped items are packed into a single ZIP with one link
+    Zip,
+}
+
+impl DropMode {
+    pub fn toggled(self) -> Self {
+        match self {
+            DropMode::Files => DropMode::Zip,
+            DropMode::Zip => DropMode::Files,
+        }
+    }
 }
 
 /// Advanced upload configuration
@@ -52,6 +241,205 @@ pub struct AdvancedConfig {
     pub parallel_uploads: u32,
     pub multipart_threshold_mb: u32,
     pub multipart_chunk_mb: u32,
+    /// Concurrent in-flight parts per multipart upload
+    #[serde(default = "default_parallel_parts")]
+    pub parallel_parts: u32,
+    /// Move the number of parallel files and parts with measured throughput and errors,
+    /// starting from `parallel_uploads` / `parallel_parts`
+    #[serde(default = "default_adaptive_concurrency")]
+    pub adaptive_concurrency: bool,
+    /// Ceiling for adaptive parallel file uploads
+    #[serde(default = "default_max_parallel")]
+    pub max_parallel_uploads: u32,
+    /// Ceiling for adaptive in-flight parts per multipart upload
+    #[serde(default = "default_max_parallel")]
+    pub max_parallel_parts: u32,
+    /// Memory shared by the chunk buffers of all multipart uploads
+    #[serde(default = "default_buffer_memory_mb")]
+    pub buffer_memory_mb: u32,
+    /// Time allowed to open a connection for an upload request
+    #[serde(default = "default_connect_timeout_secs")]
+    pub connect_timeout_secs: u32,
+    /// An upload request that moves no bytes for this long is aborted and retried
+    #[serde(default = "default_stall_timeout_secs")]
+    pub stall_timeout_secs: u32,
+    /// Which queued file gets the next free upload slot
+    #[serde(default)]
+    pub upload_order: UploadOrder,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum UploadOrder {
+    /// Smaller files first, so quick links are not stuck behind large uploads
+    #[default]
+    SmallFirst,
+    /// In the order files were queued
+    Fifo,
+}
+
+fn default_parallel_parts() -> u32 {
+    4
+}
+
+fn default_adaptive_concurrency() -> bool {
+    true
+}
+
+fn default_max_parallel() -> u32 {
+    8
+}
+
+fn default_buffer_memory_mb() -> u32 {
+    256
+}
+
+fn default_connect_timeout_secs() -> u32 {
+    10
+}
+
+fn default_stall_timeout_secs() -> u32 {
+    30
+}
+
+/// Upload rate limits in Mbit/s, shared by all uploads. 0 means unlimited.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct BandwidthConfig {
+    #[serde(default)]
+    pub limit_mbps: f64,
+    /// Used instead of `limit_mbps` on weekdays between the work hours
+    #[serde(default)]
+    pub work_hours_limit_mbps: Option<f64>,
+    /// `HH:MM`, local time
+    #[serde(default = "default_work_hours_start")]
+    pub work_hours_start: String,
+    #[serde(default = "default_work_hours_end")]
+    pub work_hours_end: String,
+}
+
+impl Default for BandwidthConfig {
+    fn default() -> Self {
+        Self {
+            limit_mbps: 0.0,
+            work_hours_limit_mbps: None,
+            work_hours_start: default_work_hours_start(),
+            work_hours_end: default_work_hours_end(),
+        }
+    }
+}
+
+fn default_work_hours_start() -> String {
+    "08:00".to_string()
+}
+
+fn default_work_hours_end() -> String {
+    "17:00".to_string()
+}
+
+impl BandwidthConfig {
+    /// Limit in bytes per second at local time `now`, `None` when unlimited.
+    pub fn limit_at(&self, now: NaiveDateTime) -> Option<u64> {
+        let mbps = self.active_limit(now);
+        (mbps > 0.0).then(|| (mbps * 1_000_000.0 / 8.0) as u64)
+    }
+
+    /// The configured limit that applies at `now`, for editing it in place.
+    pub fn active_limit_mut(&mut self, now: NaiveDateTime) -> &mut f64 {
+        let work_time = self.is_work_time(now);
+        match &mut self.work_hours_limit_mbps {
+            Some(limit) if work_time => limit,
+            _ => &mut self.limit_mbps,
+        }
+    }
+
+    fn active_limit(&self, now: NaiveDateTime) -> f64 {
+        match self.work_hours_limit_mbps {
+            Some(limit) if self.is_work_time(now) => limit,
+            _ => self.limit_mbps,
+        }
+    }
+
+    fn is_work_time(&self, now: NaiveDateTime) -> bool {
+        let Ok((start, end)) = self.work_hours() else {
+            return false;
+        };
+        if matches!(now.weekday(), Weekday::Sat | Weekday::Sun) {
+            return false;
+        }
+        window_contains(start, end, now.time())
+    }
+
+    fn work_hours(&self) -> Result<(NaiveTime, NaiveTime)> {
+        Ok((
+            parse_time(&self.work_hours_start)?,
+            parse_time(&self.work_hours_end)?,
+        ))
+    }
+}
+
+/// Daily window in which deferred uploads start, `HH:MM` local time.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct DeferredConfig {
+    #[serde(default = "default_deferred_window_start")]
+    pub window_start: String,
+    #[serde(default = "default_deferred_window_end")]
+    pub window_end: String,
+}
+
+impl Default for DeferredConfig {
+    fn default() -> Self {
+        Self {
+            window_start: default_deferred_window_start(),
+            window_end: default_deferred_window_end(),
+        }
+    }
+}
+
+fn default_deferred_window_start() -> String {
+    "20:00".to_string()
+}
+
+fn default_deferred_window_end() -> String {
+    "06:00".to_string()
+}
+
+impl DeferredConfig {
+    /// Earliest local time at or after `now` inside the window.
+    pub fn next_start(&self, now: NaiveDateTime) -> NaiveDateTime {
+        let Ok((start, end)) = self.window() else {
+            return now;
+        };
+        if window_contains(start, end, now.time()) {
+            return now;
+        }
+        let today = now.date().and_time(start);
+        if today > now {
+            today
+        } else {
+            today + chrono::Duration::days(1)
+        }
+    }
+
+    fn window(&self) -> Result<(NaiveTime, NaiveTime)> {
+        Ok((
+            parse_time(&self.window_start)?,
+            parse_time(&self.window_end)?,
+        ))
+    }
+}
+
+fn parse_time(value: &str) -> Result<NaiveTime> {
+    NaiveTime::parse_from_str(value, "%H:%M")
+        .with_context(|| format!("Invalid time {value:?}, expected HH:MM"))
+}
+
+fn window_contains(start: NaiveTime, end: NaiveTime, time: NaiveTime) -> bool {
+    if start <= end {
+        time >= start && time < end
+    } else {
+        // Window spanning midnight, e.g. 22:00-06:00
+        time >= start || time < end
+    }
 }
 
 impl Config {
@@ -103,15 +491,108 @@ impl Config {
         }
     }
 
+    /// Provider behind the `s3` backend; `None` for the others.
+    fn s3_provider(&self) -> Option<S3Provider> {
+        (self.storage.backend == StorageBackend::S3).then_some(self.storage.provider)
+    }
+
+    /// Region of the S3 backends, with the provider default when not configured.
+    pub fn s3_region(&self) -> String {
+        let region = self.oracle.region.trim();
+        match self.s3_provider().and_then(S3Provider::default_region) {
+            Some(default) if region.is_empty() => default.to_string(),
+            _ => region.to_string(),
+        }
+    }
+
+    /// Endpoint of the S3 backends, derived from the region when not configured.
+    pub fn s3_endpoint(&self) -> String {
+        let endpoint = self.oracle.endpoint.trim().trim_end_matches('/');
+        if endpoint.is_empty() {
+            if let Some(regional) = self
+                .s3_provider()
+                .and_then(|provider| provider.regional_endpoint(&self.s3_region()))
+            {
+                return regional;
+            }
+        }
+        endpoint.to_string()
+    }
+
     fn validate(&self) -> Result<()> {
-        if self.oracle.endpoint.trim().is_empty() {
-            anyhow::bail!("oracle.endpoint cannot be empty");
+        if self.storage.provider != S3Provider::Generic
+            && self.storage.backend != StorageBackend::S3
+        {
+            anyhow::bail!("storage.provider requires backend = \"s3\"");
+        }
+
+        for (name, template) in [
+            ("public_url", &self.storage.public_url),
+            ("direct_url", &self.storage.direct_url),
+        ] {
+            if let Some(template) = template {
+                if !template.starts_with("https://") && !template.starts_with("http://") {
+                    anyhow::bail!("storage.{name} must be an http(s) URL");
+                }
+            }
         }
 
-        if self.oracle.bucket.trim().is_empty() {
-            anyhow::bail!("oracle.bucket cannot be empty");
+        if self.storage.presigned_links && self.storage.backend == StorageBackend::Local {
+            anyhow::bail!("storage.presigned_links needs an S3 backend");
         }
 
+        if self.storage.presigned_links && !self.storage.link_expiry.presignable() {
+            anyhow::bail!("storage.link_expiry of presigned links cannot exceed 7d");
+        }
+
+        if self.storage.par_links {
+            if self.storage.presigned_links {
+                anyhow::bail!("storage.par_links and storage.presigned_links exclude each other");
+            }
+            if self.storage.backend != StorageBackend::Oci {
+                anyhow::bail!("storage.par_links needs the oci backend");
+            }
+            if self.oracle.namespace.is_empty() || self.oracle.region.is_empty() {
+                anyhow::bail!("storage.par_links needs oracle.namespace and oracle.region");
+            }
+            match &self.oci_api {
+                Some(api)
+                    if !api.tenancy.is_empty()
+                        && !api.user.is_empty()
+                        && !api.fingerprint.is_empty()
+                        && !api.key_file.as_os_str().is_empty() => {}
+                _ => anyhow::bail!(
+                    "storage.par_links needs oci_api.tenancy, user, fingerprint and key_file"
+                ),
+            }
+        }
+
+        if self.storage.backend == StorageBackend::Local {
+            if self.storage.local_path.is_none() {
+                anyhow::bail!("storage.local_path is required for the local backend");
+            }
+        } else {
+            if self.storage.backend == StorageBackend::S3 && self.s3_region().is_empty() {
+                anyhow::bail!("oracle.region is required for this storage provider");
+            }
+
+            if self.s3_endpoint().is_empty() {
+                anyhow::bail!("oracle.endpoint cannot be empty");
+            }
+
+            if self.oracle.bucket.trim().is_empty() {
+                anyhow::bail!("oracle.bucket cannot be empty");
+            }
+        }
+
+        self.bandwidth
+            .work_hours()
+            .context("bandwidth work hours are invalid")?;
+
+        self.deferred
+            .window()
+            .context("deferred upload window is invalid")?;
+
         Ok(())
     }
 }
@@ -155,9 +636,22 @@ multipart_chunk_mb = 5
         assert_eq!(config.oracle.region, "eu-frankfurt-1");
         assert!(config.app.auto_copy_link);
         assert!(!config.app.auto_start);
+        assert!(!config.app.reuse_existing_links);
+        assert_eq!(config.app.drop_mode, DropMode::Files);
         assert_eq!(config.advanced.parallel_uploads, 3);
         assert_eq!(config.advanced.multipart_threshold_mb, 5);
         assert_eq!(config.advanced.multipart_chunk_mb, 5);
+        assert_eq!(config.advanced.parallel_parts, 4);
+        assert!(config.advanced.adaptive_concurrency);
+        assert_eq!(config.advanced.max_parallel_uploads, 8);
+        assert_eq!(config.advanced.buffer_memory_mb, 256);
+        assert_eq!(config.advanced.stall_timeout_secs, 30);
+        assert_eq!(config.advanced.upload_order, UploadOrder::SmallFirst);
+        assert_eq!(config.bandwidth, BandwidthConfig::default());
+        assert_eq!(config.deferred, DeferredConfig::default());
+        assert_eq!(config.storage.backend, StorageBackend::Oci);
+        assert!(!config.storage.presigned_links);
+        assert_eq!(config.storage.link_expiry, LinkExpiry::Day);
     }
 
     #[test]
@@ -283,6 +777,372 @@ multipart_chunk_mb = 5
         );
     }
 
+    #[test]
+    fn test_parse_drop_mode() {
+        let config_content = r#"
+[oracle]
+endpoint = "https://test.objectstorage.eu-frankfurt-1.oraclecloud.com"
+bucket = "test-bucket"
+namespace = "test-namespace"
+region = "eu-frankfurt-1"
+
+[app]
+auto_copy_link = true
+auto_start = false
+drop_mode = "zip"
+
+[advanced]
+parallel_uploads = 3
+multipart_threshold_mb = 5
+multipart_chunk_mb = 5
+"#;
+
+        let mut temp_file = NamedTempFile::new().unwrap();
+        temp_file.write_all(config_content.as_bytes()).unwrap();
+        temp_file.flush().unwrap();
+
+        let config = Config::load(temp_file.path()).unwrap();
+        assert_eq!(config.app.drop_mode, DropMode::Zip);
+        assert_eq!(config.app.drop_mode.toggled(), DropMode::Files);
+    }
+
+    #[test]
+    fn test_local_storage_needs_no_bucket() {
+        let config_content = r#"
+[storage]
+backend = "local"
+local_path = "D:/Drop2S3"
+local_base_url = "https://files.example.pl"
+
+[app]
+auto_copy_link = true
+auto_start = false
+
+[advanced]
+parallel_uploads = 3
+multipart_threshold_mb = 5
+multipart_chunk_mb = 5
+"#;
+
+        let mut temp_file = NamedTempFile::new().unwrap();
+        temp_file.write_all(config_content.as_bytes()).unwrap();
+        temp_file.flush().unwrap();
+
+        let config = Config::load(temp_file.path()).unwrap();
+        assert_eq!(config.storage.backend, StorageBackend::Local);
+        assert_eq!(config.storage.local_path, Some(PathBuf::from("D:/Drop2S3")));
+
+        let without_path = config_content.replace("local_path = \"D:/Drop2S3\"\n", "");
+        let mut temp_file = NamedTempFile::new().unwrap();
+        temp_file.write_all(without_path.as_bytes()).unwrap();
+        temp_file.flush().unwrap();
+
+        let result = Config::load(temp_file.path());
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("storage.local_path is required"));
+
+        let bad_template = config_content.replace(
+            "local_base_url = \"https://files.example.pl\"",
+            "public_url = \"files.example.pl/{key}\"",
+        );
+        let mut temp_file = NamedTempFile::new().unwrap();
+        temp_file.write_all(bad_template.as_bytes()).unwrap();
+        temp_file.flush().unwrap();
+
+        let result = Config::load(temp_file.path());
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("storage.public_url must be an http(s) URL"));
+    }
+
+    #[test]
+    fn test_s3_provider_endpoint_and_region() {
+        let load = |storage: &str, oracle: &str| {
+            let config_content = format!(
+                r#"
+[storage]
+{storage}
+
+[oracle]
+bucket = "test-bucket"
+{oracle}
+
+[app]
+auto_copy_link = true
+auto_start = false
+
+[advanced]
+parallel_uploads = 3
+multipart_threshold_mb = 5
+multipart_chunk_mb = 5
+"#
+            );
+            let mut temp_file = NamedTempFile::new().unwrap();
+            temp_file.write_all(config_content.as_bytes()).unwrap();
+            temp_file.flush().unwrap();
+            Config::load(temp_file.path())
+        };
+
+        let aws = load(
+            "backend = \"s3\"\nprovider = \"aws\"",
+            "region = \"eu-central-1\"",
+        )
+        .unwrap();
+        assert_eq!(aws.storage.provider, S3Provider::Aws);
+        assert_eq!(aws.s3_endpoint(), "https://s3.eu-central-1.amazonaws.com");
+        assert_eq!(aws.s3_region(), "eu-central-1");
+
+        let minio = load(
+            "backend = \"s3\"\nprovider = \"minio\"",
+            "endpoint = \"http://localhost:9000/\"",
+        )
+        .unwrap();
+        assert_eq!(minio.s3_endpoint(), "http://localhost:9000");
+        assert_eq!(minio.s3_region(), "us-east-1");
+
+        let error = load("backend = \"s3\"\nprovider = \"wasabi\"", "")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("oracle.region is required"));
+
+        let error = load("backend = \"s3\"\nprovider = \"r2\"", "")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("oracle.endpoint cannot be empty"));
+
+        let error = load("provider = \"aws\"", "region = \"eu-central-1\"")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("storage.provider requires backend"));
+    }
+
+    #[test]
+    fn test_presigned_links() {
+        let config: Config = toml::from_str(
+            r#"
+[storage]
+backend = "s3"
+presigned_links = true
+link_expiry = "7d"
+
+[oracle]
+endpoint = "http://localhost:9000"
+bucket = "test-bucket"
+
+[app]
+auto_copy_link = true
+auto_start = false
+
+[advanced]
+parallel_uploads = 3
+multipart_threshold_mb = 5
+multipart_chunk_mb = 5
+"#,
+        )
+        .unwrap();
+        assert!(config.validate().is_ok());
+        assert!(config.storage.presigned_links);
+        assert_eq!(config.storage.link_expiry, LinkExpiry::Week);
+        assert_eq!(LinkExpiry::Week.duration().as_secs(), 604_800);
+
+        let mut local = config.clone();
+        local.storage.backend = StorageBackend::Local;
+        local.storage.local_path = Some(PathBuf::from("D:/Drop2S3"));
+        assert!(local
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .contains("storage.presigned_links needs an S3 backend"));
+
+        let mut long = config.clone();
+        long.storage.link_expiry = LinkExpiry::Month;
+        assert!(long
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .contains("cannot exceed 7d"));
+    }
+
+    #[test]
+    fn test_par_links_need_api_key() {
+        let config_content = r#"
+[storage]
+par_links = true
+link_expiry = "30d"
+
+[oracle]
+endpoint = "https://test.compat.objectstorage.eu-frankfurt-1.oci.customer-oci.com"
+bucket = "test-bucket"
+namespace = "test"
+region = "eu-frankfurt-1"
+
+[oci_api]
+tenancy = "ocid1.tenancy.oc1..aaa"
+user = "ocid1.user.oc1..bbb"
+fingerprint = "12:34:56"
+key_file = "oci_api_key.pem"
+
+[app]
+auto_copy_link = true
+auto_start = false
+
+[advanced]
+parallel_uploads = 3
+multipart_threshold_mb = 5
+multipart_chunk_mb = 5
+"#;
+        let config: Config = toml::from_str(config_content).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.storage.link_expiry, LinkExpiry::Month);
+        assert_eq!(
+            config.oci_api.as_ref().unwrap().key_file,
+            PathBuf::from("oci_api_key.pem")
+        );
+
+        let mut without_key = config.clone();
+        without_key.oci_api = None;
+        assert!(without_key
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .contains("storage.par_links needs oci_api"));
+
+        let mut without_namespace = config.clone();
+        without_namespace.oracle.namespace.clear();
+        assert!(without_namespace
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .contains("oracle.namespace"));
+
+        let mut s3 = config.clone();
+        s3.storage.backend = StorageBackend::S3;
+        assert!(s3
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .contains("storage.par_links needs the oci backend"));
+    }
+
+    #[test]
+    fn test_bandwidth_limit_schedule() {
+        let bandwidth = BandwidthConfig {
+            limit_mbps: 0.0,
+            work_hours_limit_mbps: Some(8.0),
+            ..BandwidthConfig::default()
+        };
+        let at = |date: &str| {
+            NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M").unwrap()
+        };
+
+        // 2026-02-06 is a Friday
+        assert_eq!(bandwidth.limit_at(at("2026-02-06 09:30")), Some(1_000_000));
+        assert_eq!(bandwidth.limit_at(at("2026-02-06 17:00")), None);
+        assert_eq!(bandwidth.limit_at(at("2026-02-06 07:59")), None);
+        assert_eq!(bandwidth.limit_at(at("2026-02-07 09:30")), None);
+
+        let overnight = BandwidthConfig {
+            limit_mbps: 20.0,
+            work_hours_limit_mbps: Some(4.0),
+            work_hours_start: "22:00".to_string(),
+            work_hours_end: "06:00".to_string(),
+        };
+        assert_eq!(overnight.limit_at(at("2026-02-05 23:00")), Some(500_000));
+        assert_eq!(overnight.limit_at(at("2026-02-06 05:00")), Some(500_000));
+        assert_eq!(overnight.limit_at(at("2026-02-06 12:00")), Some(2_500_000));
+    }
+
+    #[test]
+    fn test_active_limit_mut_edits_applicable_limit() {
+        let mut bandwidth = BandwidthConfig {
+            limit_mbps: 50.0,
+            work_hours_limit_mbps: Some(8.0),
+            ..BandwidthConfig::default()
+        };
+        let work_time = NaiveDateTime::parse_from_str("2026-02-06 10:00", "%Y-%m-%d %H:%M").unwrap();
+        let evening = NaiveDateTime::parse_from_str("2026-02-06 20:00", "%Y-%m-%d %H:%M").unwrap();
+
+        *bandwidth.active_limit_mut(work_time) = 4.0;
+        *bandwidth.active_limit_mut(evening) = 0.0;
+
+        assert_eq!(bandwidth.work_hours_limit_mbps, Some(4.0));
+        assert_eq!(bandwidth.limit_mbps, 0.0);
+    }
+
+    #[test]
+    fn test_deferred_next_start() {
+        let deferred = DeferredConfig::default();
+        let at = |date: &str| {
+            NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M").unwrap()
+        };
+
+        // Outside the window, waits for today's start
+        assert_eq!(
+            deferred.next_start(at("2026-02-06 12:00")),
+            at("2026-02-06 20:00")
+        );
+        // Inside the window on either side of midnight, starts right away
+        assert_eq!(
+            deferred.next_start(at("2026-02-06 23:15")),
+            at("2026-02-06 23:15")
+        );
+        assert_eq!(
+            deferred.next_start(at("2026-02-07 05:59")),
+            at("2026-02-07 05:59")
+        );
+        assert_eq!(
+            deferred.next_start(at("2026-02-07 06:00")),
+            at("2026-02-07 20:00")
+        );
+
+        let daytime = DeferredConfig {
+            window_start: "01:00".to_string(),
+            window_end: "05:00".to_string(),
+        };
+        assert_eq!(
+            daytime.next_start(at("2026-02-06 12:00")),
+            at("2026-02-07 01:00")
+        );
+    }
+
+    #[test]
+    fn test_invalid_work_hours_rejected() {
+        let config_content = r#"
+[oracle]
+endpoint = "https://test.objectstorage.eu-frankfurt-1.oraclecloud.com"
+bucket = "test-bucket"
+namespace = "test-namespace"
+region = "eu-frankfurt-1"
+
+[app]
+auto_copy_link = true
+auto_start = false
+
+[advanced]
+parallel_uploads = 3
+multipart_threshold_mb = 5
+multipart_chunk_mb = 5
+
+[bandwidth]
+limit_mbps = 10
+work_hours_start = "8 rano"
+"#;
+
+        let mut temp_file = NamedTempFile::new().unwrap();
+        temp_file.write_all(config_content.as_bytes()).unwrap();
+        temp_file.flush().unwrap();
+
+        let result = Config::load(temp_file.path());
+        assert!(result.is_err());
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("bandwidth work hours are invalid"));
+    }
+
     #[test]
     fn test_malformed_toml_error() {
         let config_content = r#"
@@ -0,0 +1,142 @@
+//! Index of uploaded content by SHA-256, used to hand out an existing link
+//! instead of uploading identical content again.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::json_store::JsonStore;
+
+/// Entries kept at most; the oldest uploads are forgotten first.
+const MAX_ENTRIES: usize = 10_000;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DedupEntry {
+    pub sha256: String,
+    pub bucket: String,
+    pub key: String,
+    pub url: String,
+    pub size: u64,
+    pub uploaded_at: DateTime<Utc>,
+}
+
+pub struct DedupIndex {
+    store: JsonStore<DedupEntry>,
+}
+
+impl DedupIndex {
+    pub fn new(file_path: impl AsRef<Path>) -> Result<Self> {
+        Ok(DedupIndex {
+            store: JsonStore::open(file_path, "dedup index")?,
+        })
+    }
+
+    /// Most recent upload of `sha256` content to `bucket`.
+    pub fn find(&self, bucket: &str, sha256: &str) -> Option<DedupEntry> {
+        self.store
+            .read(|entries| {
+                entries
+                    .iter()
+                    .find(|e| e.bucket == bucket && e.sha256 == sha256)
+                    .cloned()
+            })
+            .flatten()
+    }
+
+    /// Entries for objects in `bucket`.
+    pub fn entries_for(&self, bucket: &str) -> Vec<DedupEntry> {
+        self.store
+            .read(|entries| entries.iter().filter(|e| e.bucket == bucket).cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Record an upload. Replaces an older entry for the same content and bucket,
+    /// and drops the oldest entries past `MAX_ENTRIES`.
+    pub fn insert(&self, entry: DedupEntry) {
+        self.store.update(|entries| {
+            entries.retain(|e| !(e.bucket == entry.bucket && e.sha256 == entry.sha256));
+            entries.insert(0, entry);
+            entries.truncate(MAX_ENTRIES);
+        });
+    }
+
+    /// Forget an object that no longer exists in the bucket.
+    pub fn remove(&self, bucket: &str, key: &str) {
+        self.store
+            .update(|entries| entries.retain(|e| !(e.bucket == bucket && e.key == key)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn sample_entry(bucket: &str, key: &str, sha256: &str) -> DedupEntry {
+        DedupEntry {
+            sha256: sha256.to_string(),
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+            url: format!("https://example.com/{key}"),
+            size: 1024,
+            uploaded_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_find_is_scoped_to_bucket() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = DedupIndex::new(temp_dir.path().join("dedup_index.json")).unwrap();
+
+        index.insert(sample_entry("bucket-a", "2026-02-06/a.pdf", "abc"));
+
+        assert!(index.find("bucket-a", "abc").is_some());
+        assert!(index.find("bucket-b", "abc").is_none());
+        assert!(index.find("bucket-a", "def").is_none());
+    }
+
+    #[test]
+    fn test_insert_replaces_same_content() {
+        let temp_dir = TempDir::new().unwrap();
+        let index_path = temp_dir.path().join("dedup_index.json");
+
+        {
+            let index = DedupIndex::new(&index_path).unwrap();
+            index.insert(sample_entry("bucket", "2026-02-06/old.pdf", "abc"));
+            index.insert(sample_entry("bucket", "2026-02-07/new.pdf", "abc"));
+        }
+
+        let index = DedupIndex::new(&index_path).unwrap();
+        let entry = index.find("bucket", "abc").unwrap();
+        assert_eq!(entry.key, "2026-02-07/new.pdf");
+    }
+
+    #[test]
+    fn test_remove_by_key() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = DedupIndex::new(temp_dir.path().join("dedup_index.json")).unwrap();
+
+        index.insert(sample_entry("bucket", "2026-02-06/a.pdf", "abc"));
+        index.insert(sample_entry("bucket", "2026-02-06/b.pdf", "def"));
+        index.remove("bucket", "2026-02-06/a.pdf");
+
+        assert!(index.find("bucket", "abc").is_none());
+        assert!(index.find("bucket", "def").is_some());
+    }
+
+    #[test]
+    fn test_insert_keeps_newest_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = DedupIndex::new(temp_dir.path().join("dedup_index.json")).unwrap();
+
+        for i in 0..=MAX_ENTRIES {
+            index.insert(sample_entry("bucket", &format!("{i}.pdf"), &i.to_string()));
+        }
+
+        let entries = index.entries_for("bucket");
+        assert_eq!(entries.len(), MAX_ENTRIES);
+        assert!(index.find("bucket", "0").is_none());
+        assert_eq!(entries[0].key, format!("{MAX_ENTRIES}.pdf"));
+    }
+}
@@ -5,6 +5,7 @@ use tray_icon::Icon;
 pub enum IconType {
     Normal,
     Uploading,
+    Paused,
 }
 
 pub fn load_icon(icon_type: IconType) -> Result<Icon> {
@@ -20,6 +21,7 @@ fn draw_cloud_icon(size: u32, icon_type: IconType) -> Vec<u8> {
     let (r, g, b) = match icon_type {
         IconType::Normal => (180, 210, 255),
         IconType::Uploading => (80, 220, 80),
+        IconType::Paused => (240, 180, 60),
     };
 
     let cloud: &[&[u8]] = &[
@@ -81,13 +83,16 @@ mod tests {
     #[test]
     fn test_icon_type_variants() {
         assert_ne!(IconType::Normal, IconType::Uploading);
+        assert_ne!(IconType::Uploading, IconType::Paused);
     }
 
     #[test]
     fn test_icons_have_different_colors() {
         let normal = draw_cloud_icon(32, IconType::Normal);
         let uploading = draw_cloud_icon(32, IconType::Uploading);
+        let paused = draw_cloud_icon(32, IconType::Paused);
 
         assert_ne!(normal, uploading);
+        assert_ne!(uploading, paused);
     }
 }
@@ -0,0 +1,197 @@
+//! Folder enumeration for structure-preserving uploads.
+//!
+//! Hidden entries, symlinks and paths matched by `.drop2s3ignore` or `.gitignore`
+//! in the dropped folder are skipped.
+
+use anyhow::{Context, Result};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::path::{Path, PathBuf};
+use walkdir::{DirEntry, WalkDir};
+
+/// Ignore files read from the root of a dropped folder, gitignore syntax.
+const IGNORE_FILES: [&str; 2] = [".drop2s3ignore", ".gitignore"];
+
+/// File inside a dropped folder.
+#[derive(Debug, Clone)]
+pub struct FolderFile {
+    pub path: PathBuf,
+    /// Path relative to the folder root, `/`-separated
+    pub relative: String,
+}
+
+/// Regular files under `root`, sorted by relative path.
+pub fn collect_files(root: &Path) -> Result<Vec<FolderFile>> {
+    let ignore = load_ignore_rules(root);
+    let mut files = Vec::new();
+
+    let walker = WalkDir::new(root)
+        .follow_links(false)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| entry.depth() == 0 || !is_skipped(entry, &ignore));
+
+    for entry in walker {
+        let entry = entry.with_context(|| format!("Failed to read folder: {}", root.display()))?;
+        if entry.path_is_symlink() {
+            tracing::debug!(path = %entry.path().display(), "Skipping symlink");
+            continue;
+        }
+        if !entry.file_type().is_file() {
+            continue;
+        }
+
+        let relative = entry
+            .path()
+            .strip_prefix(root)
+            .context("Walked path outside of folder")?
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        files.push(FolderFile {
+            path: entry.into_path(),
+            relative,
+        });
+    }
+
+    Ok(files)
+}
+
+fn load_ignore_rules(root: &Path) -> Gitignore {
+    let mut builder = GitignoreBuilder::new(root);
+    for name in IGNORE_FILES {
+        let path = root.join(name);
+        if path.is_file() {
+            if let Some(e) = builder.add(&path) {
+                tracing::warn!(path = %path.display(), "Invalid ignore pattern: {}", e);
+            }
+        }
+    }
+    builder.build().unwrap_or_else(|e| {
+        tracing::warn!("Failed to build ignore rules: {}", e);
+        Gitignore::empty()
+    })
+}
+
+fn is_skipped(entry: &DirEntry, ignore: &Gitignore) -> bool {
+    is_hidden(entry)
+        || ignore
+            .matched(entry.path(), entry.file_type().is_dir())
+            .is_ignore()
+}
+
+/// Dot-files, plus entries with the Windows hidden attribute.
+fn is_hidden(entry: &DirEntry) -> bool {
+    if entry.file_name().to_string_lossy().starts_with('.') {
+        return true;
+    }
+
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+        const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+        if let Ok(metadata) = entry.metadata() {
+            return metadata.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0;
+        }
+    }
+
+    false
+}
+
+/// HTML listing of a folder upload, served as the folder-level link.
+pub fn index_html(folder_name: &str, files: &[(String, String)]) -> String {
+    let title = escape_html(folder_name);
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"robots\" content=\"noindex\">\n<title>{title}</title>\n</head>\n\
+         <body>\n<h1>{title}</h1>\n<ul>\n"
+    );
+    for (relative, url) in files {
+        html.push_str(&format!(
+            "<li><a href=\"{}\">{}</a></li>\n",
+            escape_html(url),
+            escape_html(relative)
+        ));
+    }
+    html.push_str("</ul>\n</body>\n</html>\n");
+    html
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn relative_paths(root: &Path) -> Vec<String> {
+        collect_files(root)
+            .unwrap()
+            .into_iter()
+            .map(|f| f.relative)
+            .collect()
+    }
+
+    #[test]
+    fn test_collect_keeps_relative_paths() {
+        let temp_dir = TempDir::new().unwrap();
+        let base = temp_dir.path();
+        fs::create_dir_all(base.join("sub/nested")).unwrap();
+        fs::write(base.join("a.txt"), b"a").unwrap();
+        fs::write(base.join("sub/b.txt"), b"b").unwrap();
+        fs::write(base.join("sub/nested/c.txt"), b"c").unwrap();
+
+        assert_eq!(
+            relative_paths(base),
+            vec!["a.txt", "sub/b.txt", "sub/nested/c.txt"]
+        );
+    }
+
+    #[test]
+    fn test_collect_skips_hidden_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let base = temp_dir.path();
+        fs::create_dir_all(base.join(".git")).unwrap();
+        fs::write(base.join(".git/config"), b"x").unwrap();
+        fs::write(base.join(".env"), b"x").unwrap();
+        fs::write(base.join("visible.txt"), b"x").unwrap();
+
+        assert_eq!(relative_paths(base), vec!["visible.txt"]);
+    }
+
+    #[test]
+    fn test_collect_applies_ignore_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let base = temp_dir.path();
+        fs::create_dir_all(base.join("build")).unwrap();
+        fs::create_dir_all(base.join("docs")).unwrap();
+        fs::write(base.join(".drop2s3ignore"), "*.log\n").unwrap();
+        fs::write(base.join(".gitignore"), "build/\n!keep.log\n").unwrap();
+        fs::write(base.join("build/out.bin"), b"x").unwrap();
+        fs::write(base.join("docs/debug.log"), b"x").unwrap();
+        fs::write(base.join("docs/readme.md"), b"x").unwrap();
+        fs::write(base.join("keep.log"), b"x").unwrap();
+
+        assert_eq!(relative_paths(base), vec!["docs/readme.md", "keep.log"]);
+    }
+
+    #[test]
+    fn test_index_html_escapes_names() {
+        let html = index_html(
+            "a<b>",
+            &[(
+                "x&y.txt".to_string(),
+                "https://example.com/x&y.txt".to_string(),
+            )],
+        );
+
+        assert!(html.contains("<title>a&lt;b&gt;</title>"));
+        assert!(html.contains("<a href=\"https://example.com/x&amp;y.txt\">x&amp;y.txt</a>"));
+    }
+}
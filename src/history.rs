@@ -5,6 +5,8 @@ use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 
+use crate::json_store::write_atomic;
+
 const MAX_FILE_SIZE: u64 = 10_485_760; // 10 MB
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -13,6 +15,46 @@ pub struct HistoryEntry {
     pub url: String,
     pub timestamp: DateTime<Utc>,
     pub size: u64,
+    /// SHA-256 of the uploaded content, verified against the object after upload
+    #[serde(default)]
+    pub sha256: Option<String>,
+    /// Identical content was already in the bucket and its link was reused
+    #[serde(default)]
+    pub dedup_hit: bool,
+    /// Direct download link, when it differs from the shared `url`
+    #[serde(default)]
+    pub direct_url: Option<String>,
+    /// Object key, needed to sign a new link
+    #[serde(default)]
+    pub key: Option<String>,
+    /// When a presigned or PAR `url` stops working
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// PAR behind `url` while it can still be revoked
+    #[serde(default)]
+    pub par_id: Option<String>,
+}
+
+impl HistoryEntry {
+    pub fn new(filename: &str, url: &str) -> Self {
+        Self {
+            filename: filename.to_string(),
+            url: url.to_string(),
+            timestamp: Utc::now(),
+            size: 0,
+            sha256: None,
+            dedup_hit: false,
+            direct_url: None,
+            key: None,
+            expires_at: None,
+            par_id: None,
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at <= Utc::now())
+    }
 }
 
 pub struct History {
@@ -38,13 +80,10 @@ impl History {
     }
 
     pub fn add(&self, filename: &str, url: &str) {
-        let entry = HistoryEntry {
-            filename: filename.to_string(),
-            url: url.to_string(),
-            timestamp: Utc::now(),
-            size: 0,
-        };
+        self.add_entry(HistoryEntry::new(filename, url));
+    }
 
+    pub fn add_entry(&self, entry: HistoryEntry) {
         // Clone entries and file_path while holding lock
         let (entries_to_save, file_path) = {
             let mut inner = match self.inner.lock() {
@@ -59,6 +98,57 @@ impl History {
         let _ = Self::save_entries_to_file(&entries_to_save, &file_path);
     }
 
+    /// Replace the link of every entry for `key`, e.g. after signing a new one.
+    pub fn update_link(
+        &self,
+        key: &str,
+        url: &str,
+        expires_at: Option<DateTime<Utc>>,
+        par_id: Option<&str>,
+    ) {
+        self.update_entries(
+            |e| e.key.as_deref() == Some(key),
+            |entry| {
+                entry.url = url.to_string();
+                entry.expires_at = expires_at;
+                entry.par_id = par_id.map(str::to_string);
+            },
+        );
+    }
+
+    /// Mark links of a revoked PAR as expired.
+    pub fn expire_par(&self, par_id: &str) {
+        let now = Utc::now();
+        self.update_entries(
+            |e| e.par_id.as_deref() == Some(par_id),
+            |entry| {
+                entry.expires_at = Some(now);
+                entry.par_id = None;
+            },
+        );
+    }
+
+    fn update_entries(
+        &self,
+        matches: impl Fn(&HistoryEntry) -> bool,
+        update: impl Fn(&mut HistoryEntry),
+    ) {
+        let (entries_to_save, file_path) = {
+            let mut inner = match self.inner.lock() {
+                Ok(guard) => guard,
+                Err(_) => return,
+            };
+            inner
+                .entries
+                .iter_mut()
+                .filter(|e| matches(e))
+                .for_each(&update);
+            (inner.entries.clone(), inner.file_path.clone())
+        };
+
+        let _ = Self::save_entries_to_file(&entries_to_save, &file_path);
+    }
+
     pub fn get_all(&self) -> Vec<HistoryEntry> {
         self.inner
             .lock()
@@ -68,7 +158,7 @@ impl History {
 
     fn save_entries_to_file(entries: &[HistoryEntry], path: &Path) -> Result<()> {
         let json = serde_json::to_string_pretty(entries)?;
-        fs::write(path, json)?;
+        write_atomic(path, json)?;
         Ok(())
     }
 }
@@ -105,7 +195,7 @@ impl HistoryInner {
 
     fn save_to_disk(&self) -> Result<()> {
         let json = serde_json::to_string_pretty(&self.entries)?;
-        fs::write(&self.file_path, json)?;
+        write_atomic(&self.file_path, json)?;
         Ok(())
     }
 }
@@ -148,6 +238,42 @@ mod tests {
         assert_eq!(entries[11].filename, "file0.txt");
     }
 
+    #[test]
+    fn test_entry_checksum_persists() {
+        let temp_dir = TempDir::new().unwrap();
+        let history_path = temp_dir.path().join("history.json");
+
+        {
+            let history = History::new(&history_path).unwrap();
+            let mut entry = HistoryEntry::new("file.txt", "https://example.com/file.txt");
+            entry.size = 42;
+            entry.sha256 = Some("ab".repeat(32));
+            history.add_entry(entry);
+        }
+
+        let history = History::new(&history_path).unwrap();
+        let entries = history.get_all();
+        assert_eq!(entries[0].size, 42);
+        assert_eq!(entries[0].sha256, Some("ab".repeat(32)));
+    }
+
+    #[test]
+    fn test_legacy_entry_without_checksum() {
+        let temp_dir = TempDir::new().unwrap();
+        let history_path = temp_dir.path().join("history.json");
+        fs::write(
+            &history_path,
+            r#"[{"filename":"a.txt","url":"https://example.com/a.txt","timestamp":"2026-02-06T10:00:00Z","size":0}]"#,
+        )
+        .unwrap();
+
+        let history = History::new(&history_path).unwrap();
+        let entries = history.get_all();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].sha256, None);
+        assert!(!entries[0].dedup_hit);
+    }
+
     #[test]
     fn test_persistence() {
         let temp_dir = TempDir::new().unwrap();
@@ -163,4 +289,55 @@ mod tests {
         assert_eq!(entries.len(), 1);
         assert_eq!(entries[0].filename, "file.txt");
     }
+
+    #[test]
+    fn test_update_link_renews_expired_entry() {
+        let temp_dir = TempDir::new().unwrap();
+        let history_path = temp_dir.path().join("history.json");
+
+        let history = History::new(&history_path).unwrap();
+        let mut entry = HistoryEntry::new("a.txt", "https://example.com/a.txt?old");
+        entry.key = Some("2026-02-06/a_0123456789abcdef.txt".to_string());
+        entry.expires_at = Some(Utc::now() - chrono::Duration::hours(1));
+        history.add_entry(entry);
+        history.add("b.txt", "https://example.com/b.txt");
+        assert!(history.get_all()[1].is_expired());
+        assert!(!history.get_all()[0].is_expired());
+
+        let expires_at = Utc::now() + chrono::Duration::days(1);
+        history.update_link(
+            "2026-02-06/a_0123456789abcdef.txt",
+            "https://example.com/a.txt?new",
+            Some(expires_at),
+            None,
+        );
+
+        let entries = History::new(&history_path).unwrap().get_all();
+        assert_eq!(entries[1].url, "https://example.com/a.txt?new");
+        assert_eq!(entries[1].expires_at, Some(expires_at));
+        assert!(!entries[1].is_expired());
+        assert_eq!(entries[0].url, "https://example.com/b.txt");
+    }
+
+    #[test]
+    fn test_expire_par_marks_revoked_links() {
+        let temp_dir = TempDir::new().unwrap();
+        let history_path = temp_dir.path().join("history.json");
+
+        let history = History::new(&history_path).unwrap();
+        let mut entry = HistoryEntry::new("a.txt", "https://example.com/p/token/a.txt");
+        entry.key = Some("2026-02-06/a_0123456789abcdef.txt".to_string());
+        entry.expires_at = Some(Utc::now() + chrono::Duration::days(30));
+        entry.par_id = Some("par/id+1=".to_string());
+        history.add_entry(entry);
+        history.add("b.txt", "https://example.com/b.txt");
+
+        history.expire_par("par/id+1=");
+
+        let entries = History::new(&history_path).unwrap().get_all();
+        assert!(entries[1].is_expired());
+        assert_eq!(entries[1].par_id, None);
+        assert!(!entries[0].is_expired());
+        assert_eq!(entries[0].expires_at, None);
+    }
 }
@@ -0,0 +1,125 @@
+//! List of records persisted as a JSON file next to the executable.
+//!
+//! Backs the upload journal, the upload queue and the dedup index. Every change
+//! rewrites the file through a temporary file renamed into place, so a crash or
+//! power loss mid-write leaves the previous version instead of a truncated file.
+
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+pub struct JsonStore<T> {
+    inner: Mutex<StoreInner<T>>,
+    /// What the file holds, for log messages
+    label: &'static str,
+}
+
+struct StoreInner<T> {
+    entries: Vec<T>,
+    file_path: PathBuf,
+}
+
+impl<T: Serialize + DeserializeOwned + Clone> JsonStore<T> {
+    /// Load the entries in `file_path`. A missing or empty file starts empty,
+    /// and so does a corrupted one, with a warning.
+    pub fn open(file_path: impl AsRef<Path>, label: &'static str) -> Result<Self> {
+        let file_path = file_path.as_ref().to_path_buf();
+        let entries = load_entries(&file_path, label)?;
+        Ok(JsonStore {
+            inner: Mutex::new(StoreInner { entries, file_path }),
+            label,
+        })
+    }
+
+    /// Result of `f` on the current entries, `None` if the lock is poisoned.
+    pub fn read<R>(&self, f: impl FnOnce(&[T]) -> R) -> Option<R> {
+        self.inner.lock().ok().map(|inner| f(&inner.entries))
+    }
+
+    pub fn get_all(&self) -> Vec<T> {
+        self.read(<[T]>::to_vec).unwrap_or_default()
+    }
+
+    /// Change the entries with `f` and save them.
+    pub fn update(&self, f: impl FnOnce(&mut Vec<T>)) {
+        // Clone entries and file_path while holding lock
+        let (entries_to_save, file_path) = {
+            let mut inner = match self.inner.lock() {
+                Ok(guard) => guard,
+                Err(_) => return, // Poisoned mutex, skip save
+            };
+            f(&mut inner.entries);
+            (inner.entries.clone(), inner.file_path.clone())
+        }; // Lock released here
+
+        let saved = serde_json::to_string_pretty(&entries_to_save)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| Ok(write_atomic(&file_path, json)?));
+        if let Err(e) = saved {
+            tracing::warn!("Failed to save {}: {}", self.label, e);
+        }
+    }
+}
+
+/// Replace `path` with `contents` through a temporary file next to it.
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> std::io::Result<()> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    fs::write(&tmp_path, contents)?;
+    fs::rename(&tmp_path, path)
+}
+
+fn load_entries<T: DeserializeOwned>(file_path: &Path, label: &str) -> Result<Vec<T>> {
+    if !file_path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = fs::read_to_string(file_path)?;
+    if content.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    match serde_json::from_str::<Vec<T>>(&content) {
+        Ok(entries) => Ok(entries),
+        Err(e) => {
+            tracing::warn!("Corrupted {} file, starting empty: {}", label, e);
+            Ok(Vec::new())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_update_persists_without_temp_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("records.json");
+
+        {
+            let store = JsonStore::<String>::open(&path, "records").unwrap();
+            store.update(|entries| entries.push("a".to_string()));
+            store.update(|entries| entries.push("b".to_string()));
+        }
+
+        let store = JsonStore::<String>::open(&path, "records").unwrap();
+        assert_eq!(store.get_all(), vec!["a", "b"]);
+        assert_eq!(store.read(|entries| entries.len()), Some(2));
+        assert!(!temp_dir.path().join("records.json.tmp").exists());
+    }
+
+    #[test]
+    fn test_corrupted_file_starts_empty() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("records.json");
+        fs::write(&path, "[\"a\", ").unwrap();
+
+        let store = JsonStore::<String>::open(&path, "records").unwrap();
+        assert!(store.get_all().is_empty());
+    }
+}
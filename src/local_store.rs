@@ -0,0 +1,493 @@
+//! `ObjectStore` that writes objects as files under a local directory, for working
+//! offline or against a network share, and for tests that need no bucket.
+//!
+//! Keys map to paths below the root. Checksums and unfinished multipart uploads
+//! live in a hidden `.drop2s3` directory next to the objects.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use futures::StreamExt;
+use md5::Md5;
+use sha2::{Digest, Sha256};
+use std::path::{Component, Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+use uuid::Uuid;
+
+use crate::object_store::{
+    encode_key, multipart_md5, ByteStream, CompletedPart, ObjectInfo, ObjectStore, PutOptions,
+};
+use crate::upload_error::UploadError;
+
+/// Directory under the root for data that is not an object.
+const META_DIR: &str = ".drop2s3";
+
+pub struct LocalStore {
+    root: PathBuf,
+    /// Prefix of links instead of `file://` URLs, e.g. a web server serving `root`
+    base_url: Option<String>,
+}
+
+impl LocalStore {
+    pub fn new(root: impl Into<PathBuf>, base_url: Option<String>) -> Result<Self> {
+        let root = root.into();
+        std::fs::create_dir_all(&root)
+            .with_context(|| format!("Failed to create storage directory: {}", root.display()))?;
+        Ok(Self {
+            root,
+            base_url: base_url.map(|url| url.trim_end_matches('/').to_string()),
+        })
+    }
+
+    /// Path of the object at `key`. Keys that would leave the root are rejected.
+    fn object_path(&self, key: &str) -> Result<PathBuf> {
+        let relative = Path::new(key);
+        let inside = relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+        if !inside || key.starts_with(META_DIR) {
+            return Err(UploadError::Rejected {
+                status: 400,
+                message: format!("Invalid object key: {key}"),
+            }
+            .into());
+        }
+        Ok(self.root.join(relative))
+    }
+
+    /// File holding the checksum of the object at `key`.
+    fn checksum_path(&self, key: &str) -> PathBuf {
+        self.root.join(META_DIR).join("sha256").join(key)
+    }
+
+    fn upload_dir(&self, upload_id: &str) -> PathBuf {
+        self.root.join(META_DIR).join("uploads").join(upload_id)
+    }
+
+    /// Move a fully written temp file into place at `key` and record its checksum.
+    async fn commit(&self, key: &str, temp: &Path, sha256: &str) -> Result<()> {
+        let path = self.object_path(key)?;
+        create_parent(&path).await?;
+        tokio::fs::rename(temp, &path)
+            .await
+            .with_context(|| format!("Failed to store object: {}", path.display()))?;
+
+        let checksum = self.checksum_path(key);
+        create_parent(&checksum).await?;
+        tokio::fs::write(&checksum, sha256)
+            .await
+            .context("Failed to store object checksum")?;
+        Ok(())
+    }
+
+    /// Temp file for an object being written, moved into place once complete.
+    fn temp_path(&self) -> PathBuf {
+        self.root
+            .join(META_DIR)
+            .join("tmp")
+            .join(Uuid::new_v4().simple().to_string())
+    }
+}
+
+#[async_trait]
+impl ObjectStore for LocalStore {
+    fn name(&self) -> String {
+        self.root.display().to_string()
+    }
+
+    fn public_url(&self, key: &str) -> String {
+        match &self.base_url {
+            Some(base_url) => format!("{base_url}/{}", encode_key(key)),
+            None => file_url(&self.root.join(key)),
+        }
+    }
+
+    async fn put(
+        &self,
+        key: &str,
+        body: ByteStream,
+        len: u64,
+        options: &PutOptions,
+    ) -> Result<Option<String>> {
+        let temp = self.temp_path();
+        let written = write_stream(&temp, body).await.and_then(|written| {
+            check_body(&written, len, options.md5.as_deref())?;
+            Ok(written)
+        });
+        match written {
+            Ok(written) => {
+                self.commit(key, &temp, &written.sha256).await?;
+                Ok(Some(written.md5))
+            }
+            Err(e) => {
+                let _ = tokio::fs::remove_file(&temp).await;
+                Err(e)
+            }
+        }
+    }
+
+    async fn initiate_multipart(&self, key: &str, _options: &PutOptions) -> Result<String> {
+        self.object_path(key)?;
+        let upload_id = Uuid::new_v4().simple().to_string();
+        tokio::fs::create_dir_all(self.upload_dir(&upload_id))
+            .await
+            .context("Failed to start multipart upload")?;
+        Ok(upload_id)
+    }
+
+    async fn put_part(
+        &self,
+        _key: &str,
+        upload_id: &str,
+        part_number: u32,
+        body: ByteStream,
+        len: u64,
+        md5: &str,
+    ) -> Result<String> {
+        let dir = self.upload_dir(upload_id);
+        if !dir.is_dir() {
+            return Err(UploadError::SessionLost.into());
+        }
+        let path = dir.join(part_number.to_string());
+        let written = write_stream(&path, body).await?;
+        if let Err(e) = check_body(&written, len, Some(md5)) {
+            let _ = tokio::fs::remove_file(&path).await;
+            return Err(e);
+        }
+        Ok(written.md5_hex)
+    }
+
+    async fn complete_multipart(
+        &self,
+        key: &str,
+        upload_id: &str,
+        parts: Vec<CompletedPart>,
+    ) -> Result<Option<String>> {
+        use base64::Engine;
+
+        let dir = self.upload_dir(upload_id);
+        if !dir.is_dir() {
+            return Err(UploadError::SessionLost.into());
+        }
+
+        let temp = self.temp_path();
+        create_parent(&temp).await?;
+        let mut output = tokio::fs::File::create(&temp)
+            .await
+            .context("Failed to create object file")?;
+        let mut sha256 = Sha256::new();
+        let mut part_md5s = Vec::with_capacity(parts.len());
+        for part in &parts {
+            let data = tokio::fs::read(dir.join(part.part_number.to_string()))
+                .await
+                .with_context(|| format!("Part {} is missing", part.part_number))?;
+            let md5 = Md5::digest(&data);
+            if format!("{md5:x}") != part.etag {
+                anyhow::bail!("Part {} does not match its ETag", part.part_number);
+            }
+            part_md5s.push(base64::engine::general_purpose::STANDARD.encode(md5));
+            sha256.update(&data);
+            output.write_all(&data).await?;
+        }
+        output.flush().await?;
+        drop(output);
+
+        self.commit(key, &temp, &format!("{:x}", sha256.finalize()))
+            .await?;
+        let _ = tokio::fs::remove_dir_all(&dir).await;
+        Ok(multipart_md5(&part_md5s))
+    }
+
+    async fn abort_multipart(&self, _key: &str, upload_id: &str) -> Result<()> {
+        match tokio::fs::remove_dir_all(self.upload_dir(upload_id)).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    async fn head(&self, key: &str) -> Result<Option<ObjectInfo>> {
+        let path = self.object_path(key)?;
+        let metadata = match tokio::fs::metadata(&path).await {
+            Ok(metadata) if metadata.is_file() => metadata,
+            Ok(_) => return Ok(None),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Some(ObjectInfo {
+            key: key.to_string(),
+            size: metadata.len(),
+            sha256: tokio::fs::read_to_string(self.checksum_path(key))
+                .await
+                .ok(),
+        }))
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        let path = self.object_path(key)?;
+        for path in [path, self.checksum_path(key)] {
+            match tokio::fs::remove_file(&path).await {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<ObjectInfo>> {
+        let root = self.root.clone();
+        let prefix = prefix.to_string();
+        tokio::task::spawn_blocking(move || {
+            let mut objects: Vec<ObjectInfo> = walkdir::WalkDir::new(&root)
+                .into_iter()
+                .filter_entry(|entry| entry.file_name() != META_DIR)
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.file_type().is_file())
+                .filter_map(|entry| {
+                    let relative = entry.path().strip_prefix(&root).ok()?;
+                    let key = relative
+                        .components()
+                        .map(|c| c.as_os_str().to_string_lossy())
+                        .collect::<Vec<_>>()
+                        .join("/");
+                    Some(ObjectInfo {
+                        size: entry.metadata().ok()?.len(),
+                        key,
+                        sha256: None,
+                    })
+                })
+                .filter(|object| object.key.starts_with(&prefix))
+                .collect();
+            objects.sort_by(|a, b| a.key.cmp(&b.key));
+            objects
+        })
+        .await
+        .context("Failed to list objects")
+    }
+}
+
+/// What was written from a body stream.
+struct Written {
+    len: u64,
+    md5: String,
+    md5_hex: String,
+    sha256: String,
+}
+
+async fn write_stream(path: &Path, mut body: ByteStream) -> Result<Written> {
+    use base64::Engine;
+
+    create_parent(path).await?;
+    let mut file = tokio::fs::File::create(path)
+        .await
+        .with_context(|| format!("Failed to create file: {}", path.display()))?;
+    let mut md5 = Md5::new();
+    let mut sha256 = Sha256::new();
+    let mut len = 0;
+    while let Some(chunk) = body.next().await {
+        let chunk = chunk?;
+        md5.update(&chunk);
+        sha256.update(&chunk);
+        len += chunk.len() as u64;
+        file.write_all(&chunk).await?;
+    }
+    file.flush().await?;
+
+    let md5 = md5.finalize();
+    Ok(Written {
+        len,
+        md5: base64::engine::general_purpose::STANDARD.encode(md5),
+        md5_hex: format!("{md5:x}"),
+        sha256: format!("{:x}", sha256.finalize()),
+    })
+}
+
+/// Reject a body that is shorter or longer than announced or fails its MD5,
+/// like an S3 server would.
+fn check_body(written: &Written, len: u64, md5: Option<&str>) -> Result<()> {
+    if written.len != len {
+        anyhow::bail!("Expected {len} bytes, received {}", written.len);
+    }
+    if md5.is_some_and(|md5| md5 != written.md5) {
+        return Err(UploadError::Rejected {
+            status: 400,
+            message: "BadDigest".to_string(),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+async fn create_parent(path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+    }
+    Ok(())
+}
+
+/// `file://` URL of an absolute path, with forward slashes on Windows too.
+fn file_url(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    if path.starts_with('/') {
+        format!("file://{path}")
+    } else {
+        format!("file:///{path}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object_store::bytes_stream;
+    use base64::Engine;
+
+    fn md5_base64(data: &[u8]) -> String {
+        base64::engine::general_purpose::STANDARD.encode(Md5::digest(data))
+    }
+
+    #[tokio::test]
+    async fn test_put_head_delete() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = LocalStore::new(dir.path(), None).unwrap();
+        let options = PutOptions {
+            content_type: "text/plain".to_string(),
+            md5: Some(md5_base64(b"hello")),
+            sha256: None,
+        };
+
+        store
+            .put("2026-02-06/a.txt", bytes_stream("hello"), 5, &options)
+            .await
+            .unwrap();
+        assert_eq!(
+            std::fs::read(dir.path().join("2026-02-06/a.txt")).unwrap(),
+            b"hello"
+        );
+
+        let info = store.head("2026-02-06/a.txt").await.unwrap().unwrap();
+        assert_eq!(info.size, 5);
+        assert_eq!(info.sha256, Some(format!("{:x}", Sha256::digest(b"hello"))));
+
+        store.delete("2026-02-06/a.txt").await.unwrap();
+        assert_eq!(store.head("2026-02-06/a.txt").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_put_rejects_corrupted_body() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = LocalStore::new(dir.path(), None).unwrap();
+        let options = PutOptions {
+            md5: Some(md5_base64(b"hello")),
+            ..PutOptions::default()
+        };
+
+        let error = store
+            .put("a.txt", bytes_stream("hellO"), 5, &options)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            UploadError::classify(&error),
+            UploadError::Rejected { status: 400, .. }
+        ));
+        assert_eq!(store.head("a.txt").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_multipart_joins_parts_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = LocalStore::new(dir.path(), None).unwrap();
+        let upload_id = store
+            .initiate_multipart("big.bin", &PutOptions::default())
+            .await
+            .unwrap();
+
+        let mut parts = Vec::new();
+        for (part_number, data) in [(2, "world"), (1, "hello ")] {
+            let etag = store
+                .put_part(
+                    "big.bin",
+                    &upload_id,
+                    part_number,
+                    bytes_stream(data),
+                    data.len() as u64,
+                    &md5_base64(data.as_bytes()),
+                )
+                .await
+                .unwrap();
+            parts.push(CompletedPart { part_number, etag });
+        }
+        parts.sort_by_key(|part| part.part_number);
+        store
+            .complete_multipart("big.bin", &upload_id, parts)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            std::fs::read(dir.path().join("big.bin")).unwrap(),
+            b"hello world"
+        );
+        let lost = store
+            .put_part(
+                "big.bin",
+                &upload_id,
+                3,
+                bytes_stream("x"),
+                1,
+                &md5_base64(b"x"),
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(UploadError::classify(&lost), UploadError::SessionLost);
+    }
+
+    #[tokio::test]
+    async fn test_list_skips_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = LocalStore::new(dir.path(), None).unwrap();
+        for key in ["2026-02-06/b.txt", "2026-02-06/a.txt", "2026-02-07/c.txt"] {
+            store
+                .put(key, bytes_stream("x"), 1, &PutOptions::default())
+                .await
+                .unwrap();
+        }
+
+        let keys: Vec<String> = store
+            .list("2026-02-06/")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|object| object.key)
+            .collect();
+        assert_eq!(keys, ["2026-02-06/a.txt", "2026-02-06/b.txt"]);
+    }
+
+    #[test]
+    fn test_keys_stay_inside_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = LocalStore::new(dir.path(), None).unwrap();
+        assert!(store.object_path("2026-02-06/a.txt").is_ok());
+        assert!(store.object_path("../a.txt").is_err());
+        assert!(store.object_path("/etc/passwd").is_err());
+        assert!(store.object_path(".drop2s3/sha256/a.txt").is_err());
+    }
+
+    #[test]
+    fn test_public_url() {
+        let dir = tempfile::tempdir().unwrap();
+        let store =
+            LocalStore::new(dir.path(), Some("https://files.example.pl/".to_string())).unwrap();
+        assert_eq!(
+            store.public_url("a/b.txt"),
+            "https://files.example.pl/a/b.txt"
+        );
+
+        assert_eq!(
+            file_url(Path::new("/srv/drop/a.txt")),
+            "file:///srv/drop/a.txt"
+        );
+        assert_eq!(
+            file_url(Path::new("C:\\Drop2S3\\a.txt")),
+            "file:///C:/Drop2S3/a.txt"
+        );
+    }
+}
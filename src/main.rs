@@ -1,26 +1,46 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod archive;
+mod concurrency;
 mod config;
+mod dedup_index;
 mod embedded_icons;
+mod folder;
 mod history;
+mod json_store;
+mod local_store;
 mod logging;
+mod network;
+mod object_store;
+mod oci_par;
+mod pause_gate;
 mod portable_crypto;
+mod rate_limit;
+mod s3_store;
+mod scheduler;
 mod shutdown_handler;
 mod single_instance;
+mod stall;
 mod startup;
 mod tray;
 mod ui;
 mod update;
 mod upload;
+mod upload_error;
+mod upload_journal;
+mod upload_queue;
 mod utils;
 
 use anyhow::{Context, Result};
+use dedup_index::DedupIndex;
 use std::sync::Arc;
 use std::time::Duration;
 
 use history::History;
 use tray::{MenuAction, TrayManager};
 use upload::{S3Client, UploadManager, UploadProgress};
+use upload_journal::UploadJournal;
+use upload_queue::UploadQueue;
 
 pub struct AppState {
     pub rt_handle: tokio::runtime::Handle,
@@ -33,6 +53,27 @@ pub struct AppState {
     pub config_path: std::path::PathBuf,
 }
 
+impl AppState {
+    /// Show deferred uploads and uploads waiting for the network in the tray, and start
+    /// deferred ones when asked from its menu.
+    pub fn sync_tray_status(&self) {
+        if TrayManager::start_deferred_requested() {
+            self.upload_manager.start_all_deferred();
+        }
+        let deferred = self.upload_manager.deferred_uploads();
+        let next = deferred.first().map(|upload| {
+            (
+                deferred.len(),
+                upload.start_at.with_timezone(&chrono::Local),
+            )
+        });
+        if let Ok(mut tray) = self.tray_manager.lock() {
+            tray.set_deferred(next);
+            tray.set_waiting_for_network(self.upload_manager.waiting_for_network());
+        }
+    }
+}
+
 fn main() -> Result<()> {
     let args: Vec<String> = std::env::args().collect();
 
@@ -64,6 +105,7 @@ fn main() -> Result<()> {
     #[allow(clippy::arc_with_non_send_sync)]
     let app_state = Arc::new(app_state);
     start_update_check(&app_state);
+    start_resume_interrupted_uploads(&app_state);
     run_main_loop(rt, app_state)?;
 
     tracing::info!("Drop2S3 exiting");
@@ -100,12 +142,26 @@ fn initialize_app_state() -> Result<(tokio::runtime::Runtime, AppState)> {
         tracing::warn!("Failed to remove autostart registry entry: {}", e);
     }
 
+    let journal_path = utils::get_exe_dir().join("upload_journal.json");
+    let journal = UploadJournal::new(&journal_path).context("Failed to load upload journal")?;
+
+    let dedup_path = utils::get_exe_dir().join("dedup_index.json");
+    let dedup_index = DedupIndex::new(&dedup_path).context("Failed to load dedup index")?;
+
     let s3_client = rt
         .block_on(S3Client::new(&config))
-        .context("Failed to create S3 client")?;
+        .context("Failed to create S3 client")?
+        .with_journal(Arc::new(journal))
+        .with_dedup_index(Arc::new(dedup_index), config.app.reuse_existing_links);
+
+    let queue_path = utils::get_exe_dir().join("upload_queue.json");
+    let queue = UploadQueue::new(&queue_path).context("Failed to load upload queue")?;
 
-    let (upload_manager, progress_rx) =
-        UploadManager::new(s3_client, config.advanced.parallel_uploads as usize, 3);
+    let (upload_manager, progress_rx) = UploadManager::new(s3_client, &config.advanced, 3);
+    let upload_manager = upload_manager
+        .with_queue(Arc::new(queue))
+        .with_deferred_window(config.deferred.clone())
+        .with_link_expiry(config.storage.link_expiry);
 
     let history_path = utils::get_exe_dir().join("history.json");
     let history = History::new(&history_path).context("Failed to load history")?;
@@ -157,6 +213,36 @@ fn start_update_check(app_state: &Arc<AppState>) {
     });
 }
 
+fn start_resume_interrupted_uploads(app_state: &Arc<AppState>) {
+    let manager = app_state.upload_manager.clone();
+    let history = app_state.history.clone();
+    app_state.rt_handle.spawn(async move {
+        manager.restore_queue().await;
+        let report = manager.resume_interrupted().await;
+        for (filename, object) in report.uploaded() {
+            tracing::info!("Resumed upload completed: {}", object.url);
+            history.add_entry(object.history_entry(filename));
+        }
+        for (filename, error) in report.failed() {
+            tracing::error!("Resuming upload of {} failed: {}", filename, error);
+        }
+        manager.prune_dedup_index().await;
+    });
+
+    let manager = app_state.upload_manager.clone();
+    let history = app_state.history.clone();
+    app_state.rt_handle.spawn(async move {
+        let report = manager.resume_deferred().await;
+        for (filename, object) in report.uploaded() {
+            tracing::info!("Deferred upload completed: {}", object.url);
+            history.add_entry(object.history_entry(filename));
+        }
+        for (filename, error) in report.failed() {
+            tracing::error!("Deferred upload of {} failed: {}", filename, error);
+        }
+    });
+}
+
 fn run_main_loop(rt: tokio::runtime::Runtime, app_state: Arc<AppState>) -> Result<()> {
     tracing::info!("Entering main loop (lightweight mode)");
 
@@ -184,6 +270,8 @@ fn run_main_loop(rt: tokio::runtime::Runtime, app_state: Arc<AppState>) -> Resul
             break;
         }
 
+        app_state.sync_tray_status();
+
         if TrayManager::should_show_window() {
             tracing::info!("Tray: show window requested");
             should_show_window = true;
@@ -194,6 +282,7 @@ fn run_main_loop(rt: tokio::runtime::Runtime, app_state: Arc<AppState>) -> Resul
             if let Ok(tray) = app_state.tray_manager.lock() {
                 match tray.handle_menu_event(&event) {
                     MenuAction::ShowWindow => should_show_window = true,
+                    MenuAction::StartDeferred => app_state.upload_manager.start_all_deferred(),
                     MenuAction::Quit => {
                         tracing::info!("Quit from menu");
                         if let Err(e) = update::UpdateManager::apply_update_on_shutdown() {
@@ -0,0 +1,169 @@
+//! Reachability of the storage endpoint.
+//!
+//! Uploads that fail because the endpoint cannot be reached mark it offline and
+//! wait here instead of failing. One of the waiters probes the endpoint at a fixed
+//! interval; once it answers, every waiter resumes.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+
+/// Pause between probes while the endpoint is unreachable.
+const PROBE_INTERVAL: Duration = Duration::from_secs(15);
+/// A probe is a single HEAD request; a slow answer still counts as unreachable.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct NetworkMonitor {
+    http: reqwest::Client,
+    endpoint: String,
+    online: watch::Sender<bool>,
+    /// Held by the waiter that is probing, so the others do not probe too
+    probe_lock: tokio::sync::Mutex<()>,
+    waiting: AtomicUsize,
+}
+
+/// Counts an upload as waiting for the network, released on drop.
+struct Waiting<'a>(&'a AtomicUsize);
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl NetworkMonitor {
+    pub fn new(http: reqwest::Client, endpoint: &str) -> Arc<Self> {
+        Arc::new(Self {
+            http,
+            endpoint: endpoint.to_string(),
+            online: watch::Sender::new(true),
+            probe_lock: tokio::sync::Mutex::new(()),
+            waiting: AtomicUsize::new(0),
+        })
+    }
+
+    /// Whether the endpoint answered the last time it was checked.
+    pub fn is_online(&self) -> bool {
+        *self.online.borrow()
+    }
+
+    /// Uploads currently held until the endpoint is reachable again.
+    pub fn waiting(&self) -> usize {
+        self.waiting.load(Ordering::SeqCst)
+    }
+
+    /// Probe the endpoint now and record the result.
+    pub async fn check(&self) -> bool {
+        let reachable = self.probe().await;
+        self.set_online(reachable);
+        reachable
+    }
+
+    fn set_online(&self, online: bool) {
+        let changed = self
+            .online
+            .send_if_modified(|current| std::mem::replace(current, online) != online);
+        if changed && online {
+            tracing::info!("Endpoint {} is reachable again", self.endpoint);
+        } else if changed {
+            tracing::warn!("Endpoint {} is unreachable", self.endpoint);
+        }
+    }
+
+    /// Wait until the endpoint is reachable. Returns immediately when online.
+    pub async fn wait_online(&self) {
+        let mut rx = self.online.subscribe();
+        if *rx.borrow_and_update() {
+            return;
+        }
+        self.waiting.fetch_add(1, Ordering::SeqCst);
+        let _waiting = Waiting(&self.waiting);
+
+        while !*rx.borrow_and_update() {
+            match self.probe_lock.try_lock() {
+                Ok(_probing) => {
+                    if !self.check().await {
+                        tokio::time::sleep(PROBE_INTERVAL).await;
+                    }
+                }
+                // Another waiter is probing; take over if it goes away
+                Err(_) => {
+                    let _ = tokio::time::timeout(PROBE_INTERVAL, rx.changed()).await;
+                }
+            }
+        }
+    }
+
+    /// Any HTTP answer, even an error status, means the endpoint is reachable.
+    async fn probe(&self) -> bool {
+        match self
+            .http
+            .head(&self.endpoint)
+            .timeout(PROBE_TIMEOUT)
+            .send()
+            .await
+        {
+            Ok(_) => true,
+            Err(e) => {
+                tracing::debug!("Probe of {} failed: {}", self.endpoint, e);
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    /// Endpoint that answers every connection with an empty 403.
+    fn answering_endpoint() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let _ = stream.read(&mut [0; 1024]);
+                let _ = stream.write_all(b"HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\n\r\n");
+            }
+        });
+        format!("http://{addr}")
+    }
+
+    /// Endpoint with nothing listening behind it.
+    fn closed_endpoint() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        format!("http://{addr}")
+    }
+
+    #[tokio::test]
+    async fn test_check_tracks_reachability() {
+        let monitor = NetworkMonitor::new(reqwest::Client::new(), &closed_endpoint());
+        assert!(monitor.is_online());
+        assert!(!monitor.check().await);
+        assert!(!monitor.is_online());
+
+        let monitor = NetworkMonitor::new(reqwest::Client::new(), &answering_endpoint());
+        monitor.set_online(false);
+        assert!(monitor.check().await);
+        assert!(monitor.is_online());
+    }
+
+    #[tokio::test]
+    async fn test_wait_online_resumes_when_reachable() {
+        let monitor = NetworkMonitor::new(reqwest::Client::new(), &answering_endpoint());
+        monitor.wait_online().await;
+
+        monitor.set_online(false);
+        assert_eq!(monitor.waiting(), 0);
+        tokio::time::timeout(Duration::from_secs(5), monitor.wait_online())
+            .await
+            .unwrap();
+        assert!(monitor.is_online());
+        assert_eq!(monitor.waiting(), 0);
+    }
+}
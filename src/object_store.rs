@@ -0,0 +1,234 @@
+//! Storage backends that uploads are written to.
+//!
+//! `S3Client` drives uploads (part layout, retries, rate limit, journal) on top of
+//! an `ObjectStore`, which only moves bytes to keys. Request bodies are streams, so
+//! the caller can count, throttle and watch them for stalls the same way for
+//! every backend.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use base64::Engine;
+use futures::stream::{BoxStream, StreamExt};
+use md5::{Digest, Md5};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use std::time::Duration;
+use tokio_util::bytes::Bytes;
+
+/// Characters escaped in a key segment: all but RFC 3986 unreserved ones.
+const KEY_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// Request body, produced in chunks as it is sent.
+pub type ByteStream = BoxStream<'static, std::io::Result<Bytes>>;
+
+/// Body of a small object that is already in memory.
+pub fn bytes_stream(bytes: impl Into<Bytes>) -> ByteStream {
+    futures::stream::once(futures::future::ready(Ok(bytes.into()))).boxed()
+}
+
+/// Properties of a new object.
+#[derive(Debug, Clone, Default)]
+pub struct PutOptions {
+    pub content_type: String,
+    /// Base64 MD5 of the body, so the store rejects a body corrupted in transit
+    pub md5: Option<String>,
+    /// Hex SHA-256 of the content, kept with the object
+    pub sha256: Option<String>,
+}
+
+/// Stored object as reported by the store.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectInfo {
+    pub key: String,
+    pub size: u64,
+    /// Checksum kept with the object, if it has one
+    pub sha256: Option<String>,
+}
+
+/// Part confirmed by the store, needed to complete a multipart upload.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompletedPart {
+    pub part_number: u32,
+    pub etag: String,
+}
+
+#[async_trait]
+pub trait ObjectStore: Send + Sync {
+    /// Bucket or directory name, keys the dedup index.
+    fn name(&self) -> String;
+
+    /// Link returned for `key`.
+    fn public_url(&self, key: &str) -> String;
+
+    /// Write `body` of `len` bytes to `key` in a single request. Returns the base64
+    /// MD5 the store computed over the bytes it received, if it reports one.
+    async fn put(
+        &self,
+        key: &str,
+        body: ByteStream,
+        len: u64,
+        options: &PutOptions,
+    ) -> Result<Option<String>>;
+
+    /// Start a multipart upload to `key` and return its upload id.
+    async fn initiate_multipart(&self, key: &str, options: &PutOptions) -> Result<String>;
+
+    /// Upload part `part_number` (1-based) and return its ETag. `md5` is the
+    /// base64 MD5 of the part.
+    async fn put_part(
+        &self,
+        key: &str,
+        upload_id: &str,
+        part_number: u32,
+        body: ByteStream,
+        len: u64,
+        md5: &str,
+    ) -> Result<String>;
+
+    /// Join `parts`, in ascending order, into the object. Returns the object's
+    /// multipart MD5 as the store computed it, see `multipart_md5`, if it reports one.
+    async fn complete_multipart(
+        &self,
+        key: &str,
+        upload_id: &str,
+        parts: Vec<CompletedPart>,
+    ) -> Result<Option<String>>;
+
+    async fn abort_multipart(&self, key: &str, upload_id: &str) -> Result<()>;
+
+    /// The object at `key`, or `None` if there is none.
+    async fn head(&self, key: &str) -> Result<Option<ObjectInfo>>;
+
+    async fn delete(&self, key: &str) -> Result<()>;
+
+    /// Objects whose key starts with `prefix`, without checksums.
+    async fn list(&self, prefix: &str) -> Result<Vec<ObjectInfo>>;
+
+    /// Signed link that reads `key` without credentials until `expires_in` passes.
+    async fn presign_get(&self, key: &str, expires_in: Duration) -> Result<String> {
+        let _ = (key, expires_in);
+        anyhow::bail!("{} cannot sign download links", self.name())
+    }
+}
+
+/// `key` as it goes into a URL path: every segment percent-encoded, `/` kept.
+pub fn encode_key(key: &str) -> String {
+    key.split('/')
+        .map(|segment| utf8_percent_encode(segment, KEY_SEGMENT).to_string())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Link to `key` from an admin template such as `https://files.example.pl/{key}`.
+/// `{bucket}` is replaced too; a template without `{key}` is a base URL the key
+/// is appended to.
+pub fn expand_url_template(template: &str, bucket: &str, key: &str) -> String {
+    let key = encode_key(key);
+    let template = template.replace("{bucket}", bucket);
+    if template.contains("{key}") {
+        template.replace("{key}", &key)
+    } else {
+        format!("{}/{key}", template.trim_end_matches('/'))
+    }
+}
+
+/// MD5 of a multipart object the way S3 and OCI compute it: the MD5 of the
+/// concatenated part MD5s followed by `-<part count>`. `part_md5s` are base64, in
+/// part order.
+pub fn multipart_md5(part_md5s: &[String]) -> Option<String> {
+    let mut md5 = Md5::new();
+    for part_md5 in part_md5s {
+        md5.update(BASE64.decode(part_md5).ok()?);
+    }
+    Some(format!(
+        "{}-{}",
+        BASE64.encode(md5.finalize()),
+        part_md5s.len()
+    ))
+}
+
+/// Base64 form of an ETag that is a hex MD5, plain or `-<part count>` multipart,
+/// as S3-compatible stores return for unencrypted objects. `None` for other ETags.
+pub fn md5_from_etag(etag: &str) -> Option<String> {
+    let etag = etag.replace("&quot;", "");
+    let etag = etag.trim_matches('"');
+    let (hex, parts) = match etag.split_once('-') {
+        Some((hex, parts)) => (hex, Some(parts.parse::<u32>().ok()?)),
+        None => (etag, None),
+    };
+    if hex.len() != 32 {
+        return None;
+    }
+    let digest = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    let md5 = BASE64.encode(digest);
+    Some(match parts {
+        Some(parts) => format!("{md5}-{parts}"),
+        None => md5,
+    })
+}
+
+const BASE64: base64::engine::GeneralPurpose = base64::engine::general_purpose::STANDARD;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_key() {
+        assert_eq!(
+            encode_key("2026-02-06/raport_abc123.pdf"),
+            "2026-02-06/raport_abc123.pdf"
+        );
+        assert_eq!(encode_key("dir/a b#1?.txt"), "dir/a%20b%231%3F.txt");
+        assert_eq!(encode_key("zółw/100%.png"), "z%C3%B3%C5%82w/100%25.png");
+    }
+
+    #[test]
+    fn test_expand_url_template() {
+        assert_eq!(
+            expand_url_template("https://files.example.pl/{key}", "b", "a/x y.png"),
+            "https://files.example.pl/a/x%20y.png"
+        );
+        assert_eq!(
+            expand_url_template("https://cdn.example.pl/", "b", "a/x.png"),
+            "https://cdn.example.pl/a/x.png"
+        );
+        assert_eq!(
+            expand_url_template("https://{bucket}.example.pl/d/{key}?dl=1", "files", "a.png"),
+            "https://files.example.pl/d/a.png?dl=1"
+        );
+    }
+
+    #[test]
+    fn test_md5_from_etag() {
+        let md5 = BASE64.encode(Md5::digest(b"hello"));
+        assert_eq!(
+            md5_from_etag("\"5d41402abc4b2a76b9719d911017c592\""),
+            Some(md5.clone())
+        );
+        assert_eq!(
+            md5_from_etag("&quot;5d41402abc4b2a76b9719d911017c592-3&quot;"),
+            Some(format!("{md5}-3"))
+        );
+        assert_eq!(md5_from_etag("\"2a1b3c4d-aaaa-bbbb\""), None);
+        assert_eq!(md5_from_etag("5d41402abc4b2a76b9719d911017c59z"), None);
+    }
+
+    #[test]
+    fn test_multipart_md5() {
+        let parts = [b"hello ".as_slice(), b"world"].map(|data| BASE64.encode(Md5::digest(data)));
+        let mut concatenated = Md5::digest(b"hello ").to_vec();
+        concatenated.extend(Md5::digest(b"world"));
+        assert_eq!(
+            multipart_md5(&parts),
+            Some(format!("{}-2", BASE64.encode(Md5::digest(&concatenated))))
+        );
+        assert_eq!(multipart_md5(&["not base64!".to_string()]), None);
+    }
+}
@@ -0,0 +1,88 @@
+//! Shared pause switch for the upload queue.
+//!
+//! Uploads check the gate before starting a file or a part, so pausing lets
+//! requests already on the wire finish and holds everything else back.
+
+use std::sync::Arc;
+use tokio::sync::watch;
+
+#[derive(Clone)]
+pub struct PauseGate {
+    tx: Arc<watch::Sender<bool>>,
+}
+
+impl Default for PauseGate {
+    fn default() -> Self {
+        Self {
+            tx: Arc::new(watch::Sender::new(false)),
+        }
+    }
+}
+
+impl PauseGate {
+    /// Returns false if already paused.
+    pub fn pause(&self) -> bool {
+        self.tx.send_if_modified(|paused| !std::mem::replace(paused, true))
+    }
+
+    /// Returns false if not paused.
+    pub fn resume(&self) -> bool {
+        self.tx.send_if_modified(|paused| std::mem::replace(paused, false))
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *self.tx.borrow()
+    }
+
+    /// Wait until the gate is open. Returns immediately when not paused.
+    pub async fn wait_resumed(&self) {
+        let mut rx = self.tx.subscribe();
+        // The sender lives in `self`, so the channel cannot close while waiting
+        let _ = rx.wait_for(|paused| !*paused).await;
+    }
+
+    /// Receiver notified on every pause and resume.
+    pub fn subscribe(&self) -> watch::Receiver<bool> {
+        self.tx.subscribe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_pause_and_resume_report_changes() {
+        let gate = PauseGate::default();
+        assert!(!gate.is_paused());
+
+        assert!(gate.pause());
+        assert!(!gate.pause());
+        assert!(gate.is_paused());
+
+        assert!(gate.resume());
+        assert!(!gate.resume());
+        assert!(!gate.is_paused());
+    }
+
+    #[tokio::test]
+    async fn test_wait_resumed_blocks_while_paused() {
+        let gate = PauseGate::default();
+        gate.wait_resumed().await;
+
+        gate.pause();
+        let blocked = tokio::time::timeout(Duration::from_millis(50), gate.wait_resumed()).await;
+        assert!(blocked.is_err());
+
+        let waiter = tokio::spawn({
+            let gate = gate.clone();
+            async move { gate.wait_resumed().await }
+        });
+        gate.resume();
+        tokio::time::timeout(Duration::from_secs(1), waiter)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+}
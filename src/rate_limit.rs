@@ -0,0 +1,143 @@
+//! Global upload rate limit shared by all uploads and parts.
+//!
+//! A token bucket that is allowed to go into debt: every caller takes its bytes
+//! immediately and sleeps until the debt is paid off at the configured rate.
+//! Parallel senders therefore queue up behind each other instead of each getting
+//! the full rate.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::config::BandwidthConfig;
+
+/// Bytes that may be sent at full speed after an idle period, in seconds of the rate.
+const BURST_SECS: f64 = 0.25;
+
+pub struct RateLimiter {
+    state: Mutex<LimiterState>,
+}
+
+struct LimiterState {
+    config: BandwidthConfig,
+    /// Bytes that can be sent right away; negative while callers wait
+    allowance: f64,
+    last_update: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(config: BandwidthConfig) -> Self {
+        Self {
+            state: Mutex::new(LimiterState {
+                config,
+                allowance: 0.0,
+                last_update: Instant::now(),
+            }),
+        }
+    }
+
+    /// Replace the limits. Applies to the next chunk of every upload.
+    pub fn set_config(&self, config: BandwidthConfig) {
+        if let Ok(mut state) = self.state.lock() {
+            state.config = config;
+        }
+    }
+
+    /// Current limit in bytes per second, `None` when unlimited.
+    pub fn current_limit(&self) -> Option<u64> {
+        self.state
+            .lock()
+            .ok()
+            .and_then(|state| state.config.limit_at(chrono::Local::now().naive_local()))
+    }
+
+    /// Wait until `bytes` may be sent.
+    pub async fn acquire(&self, bytes: u64) {
+        let wait = self.reserve(bytes, Instant::now(), chrono::Local::now().naive_local());
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    fn reserve(&self, bytes: u64, now: Instant, local_time: chrono::NaiveDateTime) -> Duration {
+        let Ok(mut state) = self.state.lock() else {
+            return Duration::ZERO;
+        };
+        let Some(rate) = state.config.limit_at(local_time) else {
+            state.allowance = 0.0;
+            state.last_update = now;
+            return Duration::ZERO;
+        };
+
+        let rate = rate as f64;
+        let elapsed = now.saturating_duration_since(state.last_update).as_secs_f64();
+        state.last_update = now;
+        state.allowance = (state.allowance + elapsed * rate).min(rate * BURST_SECS);
+        state.allowance -= bytes as f64;
+
+        if state.allowance >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-state.allowance / rate)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limited(mbps: f64) -> RateLimiter {
+        RateLimiter::new(BandwidthConfig {
+            limit_mbps: mbps,
+            ..BandwidthConfig::default()
+        })
+    }
+
+    fn noon() -> chrono::NaiveDateTime {
+        chrono::NaiveDateTime::parse_from_str("2026-02-07 12:00", "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_unlimited_never_waits() {
+        let limiter = RateLimiter::new(BandwidthConfig::default());
+        let now = Instant::now();
+
+        assert_eq!(limiter.reserve(100 * 1024 * 1024, now, noon()), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_parallel_callers_queue_up() {
+        // 8 Mbit/s = 1 MB/s
+        let now = Instant::now();
+        let limiter = limited(8.0);
+
+        let first = limiter.reserve(500_000, now, noon());
+        let second = limiter.reserve(500_000, now, noon());
+
+        assert_eq!(first, Duration::from_millis(500));
+        assert_eq!(second, Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_idle_time_refills_only_up_to_burst() {
+        let limiter = limited(8.0);
+        let start = Instant::now();
+        limiter.reserve(0, start, noon());
+
+        let later = start + Duration::from_secs(10);
+        assert_eq!(limiter.reserve(250_000, later, noon()), Duration::ZERO);
+        assert_eq!(
+            limiter.reserve(250_000, later, noon()),
+            Duration::from_millis(250)
+        );
+    }
+
+    #[test]
+    fn test_set_config_changes_rate() {
+        let limiter = limited(8.0);
+        let now = Instant::now();
+        limiter.set_config(BandwidthConfig::default());
+
+        assert_eq!(limiter.reserve(10_000_000, now, noon()), Duration::ZERO);
+    }
+}
@@ -0,0 +1,507 @@
+//! `ObjectStore` for S3-compatible object storage, including OCI.
+//!
+//! Control requests go through rust-s3. Bodies are sent through presigned URLs
+//! with our own HTTP client instead, since rust-s3 sends them as one buffer and
+//! they could not be streamed, paced or watched for stalls.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use reqwest::header::{
+    HeaderMap, HeaderName, HeaderValue, CONTENT_LENGTH, CONTENT_TYPE, ETAG, RETRY_AFTER,
+};
+use s3::creds::Credentials;
+use s3::error::S3Error;
+use s3::{Bucket, Region};
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::config::{Config, StorageBackend};
+use crate::object_store::{
+    encode_key, md5_from_etag, ByteStream, CompletedPart, ObjectInfo, ObjectStore, PutOptions,
+};
+use crate::upload_error::{parse_retry_after, UploadError};
+
+/// Validity of presigned URLs used for streamed uploads.
+const PRESIGNED_PUT_EXPIRY_SECS: u32 = 3600;
+
+/// User metadata key holding the hex SHA-256 of the uploaded content.
+const SHA256_METADATA_KEY: &str = "sha256";
+
+/// How links to objects are built.
+#[derive(Debug, Clone)]
+enum UrlLayout {
+    /// OCI native URL on the dedicated `customer-oci.com` domain
+    Oci { namespace: String, region: String },
+    /// `ENDPOINT/BUCKET/KEY`
+    PathStyle { endpoint: String },
+    /// `SCHEME://BUCKET.HOST/KEY`
+    VirtualHost { scheme: String, host: String },
+}
+
+impl UrlLayout {
+    fn virtual_host(endpoint: &str) -> Self {
+        let (scheme, host) = endpoint.split_once("://").unwrap_or(("https", endpoint));
+        UrlLayout::VirtualHost {
+            scheme: scheme.to_string(),
+            host: host.to_string(),
+        }
+    }
+}
+
+pub struct S3Store {
+    bucket: Box<Bucket>,
+    /// Client for requests that carry file data, with our own timeouts
+    http: reqwest::Client,
+    url_layout: UrlLayout,
+}
+
+impl S3Store {
+    /// Store for the bucket in `config.oracle`, addressed the way the configured
+    /// provider expects.
+    pub fn new(
+        config: &Config,
+        access_key: &str,
+        secret_key: &str,
+        http: reqwest::Client,
+    ) -> Result<Self> {
+        let credentials = Credentials::new(Some(access_key), Some(secret_key), None, None, None)
+            .context("Failed to create S3 credentials")?;
+
+        let endpoint = config.s3_endpoint();
+        let region = Region::Custom {
+            region: config.s3_region(),
+            endpoint: endpoint.clone(),
+        };
+
+        let path_style =
+            config.storage.backend != StorageBackend::S3 || config.storage.provider.path_style();
+        let mut bucket = Bucket::new(&config.oracle.bucket, region, credentials)
+            .context("Failed to create S3 bucket")?;
+        if path_style {
+            bucket = bucket.with_path_style();
+        }
+
+        let url_layout = match config.storage.backend {
+            StorageBackend::Oci => UrlLayout::Oci {
+                namespace: config.oracle.namespace.clone(),
+                region: config.oracle.region.clone(),
+            },
+            _ if path_style => UrlLayout::PathStyle { endpoint },
+            _ => UrlLayout::virtual_host(&endpoint),
+        };
+
+        Ok(Self {
+            bucket,
+            http,
+            url_layout,
+        })
+    }
+
+    /// Send a body through a presigned PUT, turning an error response into `UploadError`.
+    async fn send_presigned(
+        &self,
+        key: &str,
+        signed_headers: HeaderMap,
+        queries: Option<HashMap<String, String>>,
+        body: ByteStream,
+        len: u64,
+    ) -> Result<reqwest::Response> {
+        let url = self
+            .bucket
+            .presign_put(
+                key,
+                PRESIGNED_PUT_EXPIRY_SECS,
+                Some(signed_headers.clone()),
+                queries,
+            )
+            .await
+            .context("Failed to sign upload request")?;
+
+        let response = self
+            .http
+            .put(url)
+            .headers(signed_headers)
+            .header(CONTENT_LENGTH, len)
+            .body(reqwest::Body::wrap_stream(body))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let retry_after = response
+                .headers()
+                .get(RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(parse_retry_after);
+            let body = response.text().await.unwrap_or_default();
+            return Err(UploadError::from_response(status, &body, retry_after).into());
+        }
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl ObjectStore for S3Store {
+    fn name(&self) -> String {
+        self.bucket.name()
+    }
+
+    fn public_url(&self, key: &str) -> String {
+        let key = encode_key(key);
+        match &self.url_layout {
+            UrlLayout::Oci { namespace, region } => {
+                build_public_url(namespace, region, &self.bucket.name(), &key)
+            }
+            UrlLayout::PathStyle { endpoint } => {
+                format!("{endpoint}/{}/{key}", self.bucket.name())
+            }
+            UrlLayout::VirtualHost { scheme, host } => {
+                format!("{scheme}://{}.{host}/{key}", self.bucket.name())
+            }
+        }
+    }
+
+    async fn put(
+        &self,
+        key: &str,
+        body: ByteStream,
+        len: u64,
+        options: &PutOptions,
+    ) -> Result<Option<String>> {
+        // Checksums go into signed headers, so they must be known before the request starts
+        let mut signed_headers = HeaderMap::new();
+        signed_headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_str(&options.content_type).context("Invalid content type")?,
+        );
+        if let Some(md5) = &options.md5 {
+            signed_headers.insert(
+                HeaderName::from_static("content-md5"),
+                HeaderValue::from_str(md5)?,
+            );
+        }
+        if let Some(sha256) = &options.sha256 {
+            signed_headers.insert(sha256_metadata_header(), HeaderValue::from_str(sha256)?);
+        }
+        let response = self
+            .send_presigned(key, signed_headers, None, body, len)
+            .await?;
+
+        let headers: HashMap<String, String> = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+        let etag = headers.get(ETAG.as_str()).cloned();
+        Ok(stored_md5(&headers, "opc-content-md5", etag.as_deref()))
+    }
+
+    async fn initiate_multipart(&self, key: &str, options: &PutOptions) -> Result<String> {
+        // Metadata headers are only sent on initiate, never on the part requests
+        let mut headers = self.bucket.extra_headers().clone();
+        if let Some(sha256) = &options.sha256 {
+            headers.insert(sha256_metadata_header(), HeaderValue::from_str(sha256)?);
+        }
+        let msg = self
+            .bucket
+            .with_extra_headers(headers)?
+            .initiate_multipart_upload(key, &options.content_type)
+            .await?;
+        Ok(msg.upload_id)
+    }
+
+    async fn put_part(
+        &self,
+        key: &str,
+        upload_id: &str,
+        part_number: u32,
+        body: ByteStream,
+        len: u64,
+        md5: &str,
+    ) -> Result<String> {
+        let mut signed_headers = HeaderMap::new();
+        signed_headers.insert(
+            HeaderName::from_static("content-md5"),
+            HeaderValue::from_str(md5)?,
+        );
+        let queries = HashMap::from([
+            ("partNumber".to_string(), part_number.to_string()),
+            ("uploadId".to_string(), upload_id.to_string()),
+        ]);
+        let response = self
+            .send_presigned(key, signed_headers, Some(queries), body, len)
+            .await?;
+
+        let etag = response
+            .headers()
+            .get(ETAG)
+            .and_then(|v| v.to_str().ok())
+            .context("Part upload response has no ETag")?;
+        Ok(etag.to_string())
+    }
+
+    async fn complete_multipart(
+        &self,
+        key: &str,
+        upload_id: &str,
+        parts: Vec<CompletedPart>,
+    ) -> Result<Option<String>> {
+        let parts = parts
+            .into_iter()
+            .map(|part| s3::serde_types::Part {
+                etag: part.etag,
+                part_number: part.part_number,
+            })
+            .collect();
+        let response = self
+            .bucket
+            .complete_multipart_upload(key, upload_id, parts)
+            .await?;
+
+        // S3 returns the ETag in the XML body, OCI also as a header
+        let etag = response
+            .as_str()
+            .ok()
+            .and_then(|body| xml_element(body, "ETag"));
+        Ok(stored_md5(
+            &response.headers(),
+            "opc-multipart-md5",
+            etag.as_deref(),
+        ))
+    }
+
+    async fn abort_multipart(&self, key: &str, upload_id: &str) -> Result<()> {
+        self.bucket.abort_upload(key, upload_id).await?;
+        Ok(())
+    }
+
+    async fn head(&self, key: &str) -> Result<Option<ObjectInfo>> {
+        let head = match self.bucket.head_object(key).await {
+            Ok((head, _)) => head,
+            Err(S3Error::HttpFailWithBody(404, _)) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Some(ObjectInfo {
+            key: key.to_string(),
+            size: head
+                .content_length
+                .and_then(|len| u64::try_from(len).ok())
+                .unwrap_or_default(),
+            sha256: head
+                .metadata
+                .and_then(|mut metadata| metadata.remove(SHA256_METADATA_KEY)),
+        }))
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        self.bucket.delete_object(key).await?;
+        Ok(())
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<ObjectInfo>> {
+        let pages = self.bucket.list(prefix.to_string(), None).await?;
+        Ok(pages
+            .into_iter()
+            .flat_map(|page| page.contents)
+            .map(|object| ObjectInfo {
+                key: object.key,
+                size: object.size,
+                sha256: None,
+            })
+            .collect())
+    }
+
+    async fn presign_get(&self, key: &str, expires_in: Duration) -> Result<String> {
+        let secs = u32::try_from(expires_in.as_secs()).unwrap_or(u32::MAX);
+        self.bucket
+            .presign_get(key, secs, None)
+            .await
+            .context("Failed to sign download link")
+    }
+}
+
+/// MD5 the store computed over a written object: OCI's `opc_md5_header`, or the
+/// ETag unless the object is encrypted with KMS or a customer key, which makes the
+/// ETag something other than an MD5.
+fn stored_md5(
+    headers: &HashMap<String, String>,
+    opc_md5_header: &str,
+    etag: Option<&str>,
+) -> Option<String> {
+    if let Some(md5) = headers.get(opc_md5_header) {
+        return Some(md5.clone());
+    }
+    let encrypted = headers.contains_key("x-amz-server-side-encryption-customer-algorithm")
+        || headers
+            .get("x-amz-server-side-encryption")
+            .is_some_and(|sse| sse.starts_with("aws:kms"));
+    if encrypted {
+        return None;
+    }
+    etag.and_then(md5_from_etag)
+}
+
+/// Text of the first `<name>` element in an XML response body.
+fn xml_element(body: &str, name: &str) -> Option<String> {
+    let open = format!("<{name}>");
+    let start = body.find(&open)? + open.len();
+    let end = start + body[start..].find(&format!("</{name}>"))?;
+    Some(body[start..end].to_string())
+}
+
+fn sha256_metadata_header() -> HeaderName {
+    HeaderName::from_static("x-amz-meta-sha256")
+}
+
+fn build_public_url(namespace: &str, region: &str, bucket: &str, key: &str) -> String {
+    // https://NAMESPACE.objectstorage.REGION.oci.customer-oci.com/n/NAMESPACE/b/BUCKET/o/KEY
+    format!(
+        "https://{namespace}.objectstorage.{region}.oci.customer-oci.com/n/{namespace}/b/{bucket}/o/{key}"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store(storage: &str, oracle: &str) -> S3Store {
+        let config: Config = toml::from_str(&format!(
+            r#"
+[storage]
+{storage}
+
+[oracle]
+bucket = "my-bucket"
+namespace = "myns"
+{oracle}
+
+[app]
+auto_copy_link = true
+auto_start = false
+
+[advanced]
+parallel_uploads = 3
+multipart_threshold_mb = 5
+multipart_chunk_mb = 5
+"#
+        ))
+        .unwrap();
+        S3Store::new(&config, "access", "secret", reqwest::Client::new()).unwrap()
+    }
+
+    #[test]
+    fn test_build_public_url() {
+        let url = build_public_url(
+            "myns",
+            "eu-frankfurt-1",
+            "my-bucket",
+            "2026-02-06/test_abc123.png",
+        );
+        assert_eq!(
+            url,
+            "https://myns.objectstorage.eu-frankfurt-1.oci.customer-oci.com/n/myns/b/my-bucket/o/2026-02-06/test_abc123.png"
+        );
+    }
+
+    #[test]
+    fn test_public_url_follows_backend() {
+        let endpoint = "endpoint = \"http://localhost:9000/\"\nregion = \"eu-frankfurt-1\"";
+        assert_eq!(
+            store("backend = \"oci\"", endpoint).public_url("a/b.png"),
+            "https://myns.objectstorage.eu-frankfurt-1.oci.customer-oci.com/n/myns/b/my-bucket/o/a/b.png"
+        );
+        assert_eq!(
+            store("backend = \"s3\"", endpoint).public_url("a/b.png"),
+            "http://localhost:9000/my-bucket/a/b.png"
+        );
+    }
+
+    #[test]
+    fn test_public_url_follows_provider() {
+        let url = |provider: &str, oracle: &str| {
+            store(
+                &format!("backend = \"s3\"\nprovider = \"{provider}\""),
+                oracle,
+            )
+            .public_url("a/b.png")
+        };
+        assert_eq!(
+            url("minio", "endpoint = \"http://localhost:9000\""),
+            "http://localhost:9000/my-bucket/a/b.png"
+        );
+        assert_eq!(
+            url("aws", "region = \"eu-central-1\""),
+            "https://my-bucket.s3.eu-central-1.amazonaws.com/a/b.png"
+        );
+        assert_eq!(
+            url("backblaze", "region = \"eu-central-003\""),
+            "https://my-bucket.s3.eu-central-003.backblazeb2.com/a/b.png"
+        );
+        assert_eq!(
+            url("wasabi", "region = \"eu-central-2\""),
+            "https://my-bucket.s3.eu-central-2.wasabisys.com/a/b.png"
+        );
+        assert_eq!(
+            url(
+                "r2",
+                "endpoint = \"https://abc123.r2.cloudflarestorage.com\""
+            ),
+            "https://abc123.r2.cloudflarestorage.com/my-bucket/a/b.png"
+        );
+    }
+
+    #[test]
+    fn test_stored_md5() {
+        let etag = "\"5d41402abc4b2a76b9719d911017c592\"";
+        let md5 = "XUFAKrxLKna5cZ2REBfFkg==";
+        let headers = |pairs: &[(&str, &str)]| {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<HashMap<_, _>>()
+        };
+
+        assert_eq!(
+            stored_md5(&headers(&[]), "opc-content-md5", Some(etag)),
+            Some(md5.to_string())
+        );
+        assert_eq!(
+            stored_md5(
+                &headers(&[("opc-content-md5", "b3BjLW1kNQ==")]),
+                "opc-content-md5",
+                Some("\"4d7c8b3f-uuid\"")
+            ),
+            Some("b3BjLW1kNQ==".to_string())
+        );
+        assert_eq!(
+            stored_md5(
+                &headers(&[("x-amz-server-side-encryption", "aws:kms")]),
+                "opc-content-md5",
+                Some(etag)
+            ),
+            None
+        );
+        assert_eq!(
+            xml_element(
+                "<CompleteMultipartUploadResult><ETag>&quot;abc-2&quot;</ETag></CompleteMultipartUploadResult>",
+                "ETag"
+            ),
+            Some("&quot;abc-2&quot;".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_presign_get() {
+        let store = store(
+            "backend = \"s3\"",
+            "endpoint = \"http://localhost:9000\"\nregion = \"us-east-1\"",
+        );
+        let url = store
+            .presign_get("a/b.png", Duration::from_secs(3600))
+            .await
+            .unwrap();
+        assert!(url.starts_with("http://localhost:9000/my-bucket/a/b.png?"));
+        assert!(url.contains("X-Amz-Expires=3600"));
+        assert!(url.contains("X-Amz-Signature="));
+    }
+}
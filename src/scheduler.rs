@@ -0,0 +1,287 @@
+//! Order in which queued files get an upload slot.
+//!
+//! File jobs are registered when queued and compete for slots under the file
+//! concurrency limit. A free slot goes to the best competing job: moved to the
+//! front by the user first, then higher priority, then smallest first or queue
+//! order depending on the policy. Jobs that hold a slot or wait between retries
+//! do not compete, so they never hold the queue up.
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tokio::sync::oneshot;
+
+use crate::concurrency::AdaptiveLimit;
+use crate::config::UploadOrder;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum Priority {
+    #[default]
+    Normal,
+    /// Ahead of normal files, e.g. pasted screenshots
+    High,
+}
+
+pub struct Scheduler {
+    limit: AdaptiveLimit,
+    order: UploadOrder,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    jobs: HashMap<String, Job>,
+    running: usize,
+    next_seq: u64,
+    next_promotion: u64,
+}
+
+struct Job {
+    priority: Priority,
+    /// Set when moved to the front; the latest move wins
+    promoted: Option<u64>,
+    size: u64,
+    seq: u64,
+    state: JobState,
+}
+
+enum JobState {
+    /// Queued but not asking for a slot yet; keeps its place in line
+    Queued,
+    Waiting(oneshot::Sender<()>),
+    Running,
+    /// Between retries, or gave up waiting
+    Idle,
+}
+
+impl State {
+    fn insert(&mut self, file_id: &str, priority: Priority, size: u64) {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.jobs.insert(
+            file_id.to_string(),
+            Job {
+                priority,
+                promoted: None,
+                size,
+                seq,
+                state: JobState::Queued,
+            },
+        );
+    }
+}
+
+impl Job {
+    fn competes(&self) -> bool {
+        matches!(self.state, JobState::Queued | JobState::Waiting(_))
+    }
+}
+
+/// Upload slot held by a file job, returned to the scheduler on drop.
+pub struct Ticket<'a> {
+    scheduler: &'a Scheduler,
+    file_id: String,
+}
+
+impl Drop for Ticket<'_> {
+    fn drop(&mut self) {
+        let Ok(mut state) = self.scheduler.state.lock() else {
+            return;
+        };
+        if let Some(job) = state.jobs.get_mut(&self.file_id) {
+            // Also reached when the wait was abandoned after the slot was granted
+            if matches!(
+                std::mem::replace(&mut job.state, JobState::Idle),
+                JobState::Running
+            ) {
+                state.running -= 1;
+            }
+        }
+        self.scheduler.dispatch(&mut state);
+    }
+}
+
+impl Scheduler {
+    pub fn new(limit: AdaptiveLimit, order: UploadOrder) -> Self {
+        Self {
+            limit,
+            order,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// Put a file job in line. `size` orders it under the small-files-first policy.
+    pub fn add(&self, file_id: &str, priority: Priority, size: u64) {
+        if let Ok(mut state) = self.state.lock() {
+            state.insert(file_id, priority, size);
+        }
+    }
+
+    /// Wait until `file_id` is first in line and a slot is free.
+    pub async fn acquire(&self, file_id: &str) -> Ticket<'_> {
+        let (tx, rx) = oneshot::channel();
+        if let Ok(mut state) = self.state.lock() {
+            if !state.jobs.contains_key(file_id) {
+                // Size unknown, so it lines up behind known files under small-first
+                state.insert(file_id, Priority::Normal, u64::MAX);
+            }
+            if let Some(job) = state.jobs.get_mut(file_id) {
+                job.state = JobState::Waiting(tx);
+            }
+            self.dispatch(&mut state);
+        }
+        // Created before waiting so an abandoned wait still gives the slot back
+        let ticket = Ticket {
+            scheduler: self,
+            file_id: file_id.to_string(),
+        };
+        let _ = rx.await;
+        ticket
+    }
+
+    /// Move a queued job ahead of everything else. Returns false if it is not waiting.
+    pub fn move_to_front(&self, file_id: &str) -> bool {
+        let Ok(mut state) = self.state.lock() else {
+            return false;
+        };
+        let promotion = state.next_promotion;
+        let Some(job) = state.jobs.get_mut(file_id).filter(|job| job.competes()) else {
+            return false;
+        };
+        job.promoted = Some(promotion);
+        state.next_promotion += 1;
+        true
+    }
+
+    /// Drop a finished job from the line.
+    pub fn remove(&self, file_id: &str) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        if let Some(job) = state.jobs.remove(file_id) {
+            if matches!(job.state, JobState::Running) {
+                state.running -= 1;
+            }
+        }
+        self.dispatch(&mut state);
+    }
+
+    /// Report a finished upload to the concurrency limit; a raised limit starts
+    /// waiting jobs right away.
+    pub fn record(&self, bytes: u64, succeeded: bool) {
+        self.limit.record(bytes, succeeded);
+        if let Ok(mut state) = self.state.lock() {
+            self.dispatch(&mut state);
+        }
+    }
+
+    /// Hand free slots to the best competing jobs. A slot is kept free when the best
+    /// job has not asked for it yet, so it cannot be overtaken in the meantime.
+    fn dispatch(&self, state: &mut State) {
+        while state.running < self.limit.limit() {
+            let Some(file_id) = self.next_in_line(state) else {
+                return;
+            };
+            let Some(job) = state.jobs.get_mut(&file_id) else {
+                return;
+            };
+            match std::mem::replace(&mut job.state, JobState::Running) {
+                JobState::Waiting(tx) => {
+                    if tx.send(()).is_ok() {
+                        state.running += 1;
+                    } else {
+                        job.state = JobState::Idle;
+                    }
+                }
+                other => {
+                    job.state = other;
+                    return;
+                }
+            }
+        }
+    }
+
+    fn next_in_line(&self, state: &State) -> Option<String> {
+        state
+            .jobs
+            .iter()
+            .filter(|(_, job)| job.competes())
+            .min_by_key(|(_, job)| {
+                let size = match self.order {
+                    UploadOrder::SmallFirst => job.size,
+                    UploadOrder::Fifo => 0,
+                };
+                (Reverse(job.promoted), Reverse(job.priority), size, job.seq)
+            })
+            .map(|(file_id, _)| file_id.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn scheduler(order: UploadOrder) -> Scheduler {
+        Scheduler::new(AdaptiveLimit::new("test", 1, 1, false), order)
+    }
+
+    async fn is_blocked(ticket: impl std::future::Future) -> bool {
+        tokio::time::timeout(Duration::from_millis(50), ticket)
+            .await
+            .is_err()
+    }
+
+    #[tokio::test]
+    async fn test_small_files_first() {
+        let scheduler = scheduler(UploadOrder::SmallFirst);
+        scheduler.add("iso", Priority::Normal, 3 << 30);
+        scheduler.add("png", Priority::Normal, 200 << 10);
+
+        assert!(is_blocked(scheduler.acquire("iso")).await);
+        let png = scheduler.acquire("png").await;
+        assert!(is_blocked(scheduler.acquire("iso")).await);
+
+        drop(png);
+        scheduler.remove("png");
+        assert!(!is_blocked(scheduler.acquire("iso")).await);
+    }
+
+    #[tokio::test]
+    async fn test_fifo_keeps_queue_order() {
+        let scheduler = scheduler(UploadOrder::Fifo);
+        scheduler.add("iso", Priority::Normal, 3 << 30);
+        scheduler.add("png", Priority::Normal, 200 << 10);
+
+        assert!(is_blocked(scheduler.acquire("png")).await);
+        let _iso = scheduler.acquire("iso").await;
+    }
+
+    #[tokio::test]
+    async fn test_priority_and_move_to_front() {
+        let scheduler = scheduler(UploadOrder::Fifo);
+        scheduler.add("a", Priority::Normal, 1);
+        scheduler.add("b", Priority::Normal, 1);
+        scheduler.add("screenshot", Priority::High, 1);
+
+        assert!(is_blocked(scheduler.acquire("a")).await);
+        assert!(scheduler.move_to_front("b"));
+        assert!(is_blocked(scheduler.acquire("screenshot")).await);
+
+        let b = scheduler.acquire("b").await;
+        assert!(!scheduler.move_to_front("b"), "already running");
+        drop(b);
+        scheduler.remove("b");
+        let _screenshot = scheduler.acquire("screenshot").await;
+    }
+
+    #[tokio::test]
+    async fn test_job_between_retries_does_not_block() {
+        let scheduler = scheduler(UploadOrder::Fifo);
+        scheduler.add("a", Priority::Normal, 1);
+        scheduler.add("b", Priority::Normal, 1);
+
+        drop(scheduler.acquire("a").await);
+        assert!(!is_blocked(scheduler.acquire("b")).await);
+    }
+}
@@ -0,0 +1,138 @@
+//! Watchdog for upload requests whose connection stops moving bytes.
+//!
+//! The request body touches a `StallTimer` every time a chunk is handed to the
+//! connection. When nothing moves for the stall timeout the request is dropped
+//! with a network error, so the retry logic treats it like any transient failure.
+//! Time spent waiting for the rate limiter does not count as stalled.
+
+use anyhow::Result;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::upload_error::UploadError;
+
+pub struct StallTimer {
+    state: Mutex<TimerState>,
+}
+
+struct TimerState {
+    last_progress: Instant,
+    /// Open `Hold`s; the clock stands still while any exist
+    holds: usize,
+}
+
+/// Keeps a `StallTimer` from running out, released on drop.
+pub struct Hold<'a> {
+    timer: &'a StallTimer,
+}
+
+impl Drop for Hold<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.timer.state.lock() {
+            state.holds -= 1;
+            state.last_progress = Instant::now();
+        }
+    }
+}
+
+impl Default for StallTimer {
+    fn default() -> Self {
+        Self {
+            state: Mutex::new(TimerState {
+                last_progress: Instant::now(),
+                holds: 0,
+            }),
+        }
+    }
+}
+
+impl StallTimer {
+    /// Record that bytes moved.
+    pub fn touch(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.last_progress = Instant::now();
+        }
+    }
+
+    /// Stop the clock for a deliberate wait; it restarts when the hold is dropped.
+    pub fn hold(&self) -> Hold<'_> {
+        if let Ok(mut state) = self.state.lock() {
+            state.holds += 1;
+        }
+        Hold { timer: self }
+    }
+
+    /// Time since bytes last moved, zero while held.
+    fn idle(&self, now: Instant) -> Duration {
+        match self.state.lock() {
+            Ok(state) if state.holds == 0 => now.saturating_duration_since(state.last_progress),
+            _ => Duration::ZERO,
+        }
+    }
+}
+
+/// Run `request` until it finishes or `timer` sees no progress for `timeout`.
+pub async fn watch<T>(
+    request: impl Future<Output = Result<T>>,
+    timer: &StallTimer,
+    timeout: Duration,
+) -> Result<T> {
+    tokio::pin!(request);
+    loop {
+        let idle = timer.idle(Instant::now());
+        if idle >= timeout {
+            return Err(UploadError::Network(format!(
+                "no data sent for {}s, connection stalled",
+                timeout.as_secs()
+            ))
+            .into());
+        }
+        tokio::select! {
+            result = &mut request => return result,
+            () = tokio::time::sleep(timeout - idle) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_stalled_request_is_aborted() {
+        let timer = StallTimer::default();
+        let stalled = std::future::pending::<Result<()>>();
+
+        let error = watch(stalled, &timer, Duration::from_millis(50))
+            .await
+            .unwrap_err();
+        assert!(UploadError::classify(&error).is_retryable());
+    }
+
+    #[tokio::test]
+    async fn test_progress_keeps_request_alive() {
+        let timer = StallTimer::default();
+        let request = async {
+            for _ in 0..5 {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                timer.touch();
+            }
+            Ok(())
+        };
+
+        watch(request, &timer, Duration::from_millis(50)).await.unwrap();
+    }
+
+    #[test]
+    fn test_hold_stops_the_clock() {
+        let timer = StallTimer::default();
+        let later = Instant::now() + Duration::from_secs(60);
+
+        let hold = timer.hold();
+        assert_eq!(timer.idle(later), Duration::ZERO);
+
+        drop(hold);
+        assert!(timer.idle(later) > Duration::from_secs(59));
+    }
+}
@@ -1,4 +1,5 @@
 use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::OnceLock;
 use tray_icon::{
@@ -10,8 +11,12 @@ use crate::embedded_icons::{self, IconType};
 
 static QUIT_ITEM_ID: OnceLock<MenuId> = OnceLock::new();
 static SHOW_ITEM_ID: OnceLock<MenuId> = OnceLock::new();
+static START_DEFERRED_ITEM_ID: OnceLock<MenuId> = OnceLock::new();
 static SHOW_WINDOW_REQUESTED: AtomicBool = AtomicBool::new(false);
 static QUIT_REQUESTED: AtomicBool = AtomicBool::new(false);
+static START_DEFERRED_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+const TOOLTIP: &str = "Drop2S3 - Przeciągnij pliki tutaj";
 
 fn show_main_window() {
     SHOW_WINDOW_REQUESTED.store(true, Ordering::SeqCst);
@@ -23,6 +28,11 @@ pub struct TrayManager {
     menu: Menu,
     show_item_id: MenuId,
     quit_item_id: MenuId,
+    start_deferred_item: MenuItem,
+    /// Waiting deferred uploads and the earliest planned start, as last shown
+    deferred: Option<(usize, DateTime<Local>)>,
+    /// Uploads held until the endpoint is reachable, as last shown
+    waiting_for_network: usize,
 }
 
 impl TrayManager {
@@ -34,6 +44,7 @@ impl TrayManager {
         let menu = Menu::new();
 
         let show_item = MenuItem::new("Pokaż okno", true, None);
+        let start_deferred_item = MenuItem::new("Wyślij zaplanowane teraz", false, None);
         let quit_item = MenuItem::new("Zamknij", true, None);
 
         let show_item_id = show_item.id().clone();
@@ -41,6 +52,7 @@ impl TrayManager {
 
         let _ = QUIT_ITEM_ID.set(quit_item_id.clone());
         let _ = SHOW_ITEM_ID.set(show_item_id.clone());
+        let _ = START_DEFERRED_ITEM_ID.set(start_deferred_item.id().clone());
 
         MenuEvent::set_event_handler(Some(|event: MenuEvent| {
             if let Some(quit_id) = QUIT_ITEM_ID.get() {
@@ -54,6 +66,11 @@ impl TrayManager {
                     show_main_window();
                 }
             }
+            if let Some(start_id) = START_DEFERRED_ITEM_ID.get() {
+                if event.id == *start_id {
+                    START_DEFERRED_REQUESTED.store(true, Ordering::SeqCst);
+                }
+            }
         }));
 
         TrayIconEvent::set_event_handler(Some(|event: TrayIconEvent| {
@@ -72,6 +89,8 @@ impl TrayManager {
 
         menu.append(&show_item)
             .context("Failed to add 'Pokaż okno' to menu")?;
+        menu.append(&start_deferred_item)
+            .context("Failed to add 'Wyślij zaplanowane teraz' to menu")?;
         menu.append(&quit_item)
             .context("Failed to add 'Zamknij' to menu")?;
 
@@ -81,7 +100,7 @@ impl TrayManager {
             .with_menu(Box::new(menu.clone()))
             .with_menu_on_left_click(false)
             .with_icon(icon)
-            .with_tooltip("Drop2S3 - Przeciągnij pliki tutaj")
+            .with_tooltip(TOOLTIP)
             .build()
             .context("Failed to build tray icon")?;
 
@@ -90,6 +109,9 @@ impl TrayManager {
             menu,
             show_item_id,
             quit_item_id,
+            start_deferred_item,
+            deferred: None,
+            waiting_for_network: 0,
         })
     }
 
@@ -114,11 +136,14 @@ impl TrayManager {
 
     /// Processes menu item events (menu clicks)
     ///
-    /// Handles: "Pokaż okno", "Zamknij"
+    /// Handles: "Pokaż okno", "Wyślij zaplanowane teraz", "Zamknij"
     pub fn handle_menu_event(&self, event: &MenuEvent) -> MenuAction {
         if event.id == self.show_item_id {
             tracing::info!("Menu: Pokaż okno clicked");
             MenuAction::ShowWindow
+        } else if event.id == *self.start_deferred_item.id() {
+            tracing::info!("Menu: Wyślij zaplanowane teraz clicked");
+            MenuAction::StartDeferred
         } else if event.id == self.quit_item_id {
             tracing::info!("Menu: Zamknij clicked");
             MenuAction::Quit
@@ -136,6 +161,37 @@ impl TrayManager {
         QUIT_REQUESTED.load(Ordering::SeqCst)
     }
 
+    pub fn start_deferred_requested() -> bool {
+        START_DEFERRED_REQUESTED.swap(false, Ordering::SeqCst)
+    }
+
+    /// Show waiting deferred uploads with their earliest planned start in the tooltip
+    /// and enable starting them from the menu.
+    pub fn set_deferred(&mut self, deferred: Option<(usize, DateTime<Local>)>) {
+        if deferred == self.deferred {
+            return;
+        }
+        self.start_deferred_item.set_enabled(deferred.is_some());
+        self.deferred = deferred;
+        self.update_tooltip();
+    }
+
+    /// Show uploads held while the endpoint is unreachable in the tooltip.
+    pub fn set_waiting_for_network(&mut self, count: usize) {
+        if count == self.waiting_for_network {
+            return;
+        }
+        self.waiting_for_network = count;
+        self.update_tooltip();
+    }
+
+    fn update_tooltip(&self) {
+        let tooltip = tooltip(self.deferred, self.waiting_for_network);
+        if let Err(e) = self.tray_icon.set_tooltip(Some(tooltip)) {
+            tracing::warn!("Failed to update tray tooltip: {}", e);
+        }
+    }
+
     /// Polls menu events
     ///
     /// Returns Some(event) if event available, None otherwise
@@ -152,10 +208,29 @@ impl TrayManager {
     }
 }
 
+fn tooltip(deferred: Option<(usize, DateTime<Local>)>, waiting_for_network: usize) -> String {
+    let mut parts = Vec::new();
+    if waiting_for_network > 0 {
+        parts.push(format!("brak sieci, czeka: {waiting_for_network}"));
+    }
+    if let Some((count, start_at)) = deferred {
+        parts.push(format!(
+            "zaplanowane: {count}, start {}",
+            start_at.format("%H:%M")
+        ));
+    }
+    if parts.is_empty() {
+        TOOLTIP.to_string()
+    } else {
+        format!("Drop2S3 - {}", parts.join("; "))
+    }
+}
+
 /// Actions triggered by menu items
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MenuAction {
     ShowWindow,
+    StartDeferred,
     Quit,
     None,
 }
@@ -163,10 +238,16 @@ pub enum MenuAction {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::Timelike;
 
     #[test]
     fn test_menu_action_variants() {
-        let actions = [MenuAction::ShowWindow, MenuAction::Quit, MenuAction::None];
+        let actions = [
+            MenuAction::ShowWindow,
+            MenuAction::StartDeferred,
+            MenuAction::Quit,
+            MenuAction::None,
+        ];
 
         for action in &actions {
             assert_eq!(*action, *action);
@@ -180,6 +261,21 @@ mod tests {
         assert_eq!(action, cloned);
     }
 
+    #[test]
+    fn test_tooltip_lists_waiting_uploads() {
+        assert_eq!(tooltip(None, 0), TOOLTIP);
+        assert_eq!(tooltip(None, 2), "Drop2S3 - brak sieci, czeka: 2");
+
+        let start_at = Local::now()
+            .with_hour(20)
+            .and_then(|t| t.with_minute(0))
+            .unwrap();
+        assert_eq!(
+            tooltip(Some((3, start_at)), 1),
+            "Drop2S3 - brak sieci, czeka: 1; zaplanowane: 3, start 20:00"
+        );
+    }
+
     #[test]
     fn test_menu_action_debug() {
         let action = MenuAction::ShowWindow;
@@ -1,16 +1,22 @@
 use anyhow::{Context, Result};
+use chrono::{DateTime, Datelike, Utc};
 use eframe::egui;
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+use crate::config::{DropMode, LinkExpiry};
 use crate::embedded_icons::IconType;
+use crate::oci_par::Par;
+use crate::scheduler::Priority;
 use crate::tray::TrayManager;
-use crate::upload::UploadProgress;
+use crate::upload::{BatchReport, UploadProgress, UploadStatus};
 use crate::AppState;
 
 const WINDOW_SIZE: [f32; 2] = [320.0, 290.0];
+/// Rows of the upload queue and of failed uploads shown at once.
+const MAX_QUEUE_ROWS: usize = 3;
 
 #[derive(Clone, PartialEq)]
 pub enum UpdateState {
@@ -113,9 +119,37 @@ struct DropZoneApp {
     upload_started_at: Option<Instant>,
     last_error: Arc<std::sync::Mutex<Option<(String, Instant)>>>,
     upload_queue: HashMap<String, UploadProgress>,
+    /// Failed and cancelled uploads that can still be retried, newest first
+    failed_uploads: Vec<UploadProgress>,
     total_files_count: usize,
     completed_files_count: usize,
     last_window_pos: Option<egui::Pos2>,
+    tray_icon: IconType,
+    /// Dropped items wait for the deferred upload window
+    defer_drops: bool,
+    last_batch: Arc<std::sync::Mutex<Option<BatchResult>>>,
+    /// PARs fetched with "Odswiez", none until the list is first loaded
+    active_pars: Arc<std::sync::Mutex<Option<Vec<Par>>>>,
+}
+
+/// Outcome of the last batch of dropped files, kept so its links can be copied at once.
+struct BatchResult {
+    urls: Vec<String>,
+    failed: usize,
+    cancelled: usize,
+}
+
+impl BatchResult {
+    fn from_report(report: &BatchReport) -> Self {
+        Self {
+            urls: report
+                .uploaded()
+                .map(|(_, object)| object.url.clone())
+                .collect(),
+            failed: report.failed().count(),
+            cancelled: report.cancelled_count(),
+        }
+    }
 }
 
 impl DropZoneApp {
@@ -129,9 +163,14 @@ impl DropZoneApp {
             upload_started_at: None,
             last_error: Arc::new(std::sync::Mutex::new(None)),
             upload_queue: HashMap::new(),
+            failed_uploads: Vec::new(),
             total_files_count: 0,
             completed_files_count: 0,
             last_window_pos: None,
+            tray_icon: IconType::Normal,
+            defer_drops: false,
+            last_batch: Arc::new(std::sync::Mutex::new(None)),
+            active_pars: Arc::new(std::sync::Mutex::new(None)),
         }
     }
 }
@@ -140,17 +179,22 @@ impl eframe::App for DropZoneApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         if TrayManager::quit_requested() {
             tracing::info!("Quit requested via tray");
+            // Uploads are not cancelled: multipart sessions stay in the journal and resume on next start
             self.should_exit = true;
-            self.app_state.upload_manager.cancel();
         }
 
         while let Some(event) = TrayManager::poll_menu_event() {
             if let Ok(tray) = self.app_state.tray_manager.lock() {
                 use crate::tray::MenuAction;
-                if tray.handle_menu_event(&event) == MenuAction::Quit {
-                    tracing::info!("Quit from menu event");
-                    self.should_exit = true;
-                    self.app_state.upload_manager.cancel();
+                match tray.handle_menu_event(&event) {
+                    MenuAction::Quit => {
+                        tracing::info!("Quit from menu event");
+                        self.should_exit = true;
+                    }
+                    MenuAction::StartDeferred => {
+                        self.app_state.upload_manager.start_all_deferred();
+                    }
+                    MenuAction::ShowWindow | MenuAction::None => {}
                 }
             }
         }
@@ -161,12 +205,18 @@ impl eframe::App for DropZoneApp {
         }
 
         self.process_upload_events();
+        // The main loop that normally keeps the tray current waits while the window is open
+        self.app_state.sync_tray_status();
 
         egui::CentralPanel::default().show(ctx, |ui| {
             self.render_update_status(ui);
             self.render_drop_zone(ctx, ui);
             self.render_upload_progress(ui);
+            self.render_deferred_uploads(ui);
+            self.render_failed_uploads(ui);
+            self.render_batch_result(ui);
             self.render_history(ui);
+            self.render_active_pars(ui);
             self.render_feedback(ui);
             self.render_version(ui);
         });
@@ -184,11 +234,13 @@ impl DropZoneApp {
             return;
         };
 
+        let mut received = false;
         while let Ok(progress) = rx.try_recv() {
-            use crate::upload::UploadStatus;
+            received = true;
 
             match &progress.status {
                 UploadStatus::Queued => {
+                    self.failed_uploads.retain(|p| p.file_id != progress.file_id);
                     self.upload_queue
                         .insert(progress.file_id.clone(), progress.clone());
                     self.total_files_count = self.upload_queue.len();
@@ -196,21 +248,24 @@ impl DropZoneApp {
                     if !self.is_uploading {
                         self.is_uploading = true;
                         self.upload_started_at = Some(Instant::now());
-                        if let Ok(mut tray) = self.app_state.tray_manager.lock() {
-                            if let Err(e) = tray.set_icon(IconType::Uploading) {
-                                tracing::error!("Failed to set uploading icon: {}", e);
-                            }
-                        }
                     }
                 }
-                UploadStatus::Uploading => {
+                UploadStatus::Uploading
+                | UploadStatus::Paused
+                | UploadStatus::WaitingForNetwork => {
                     self.upload_queue
                         .insert(progress.file_id.clone(), progress.clone());
                     self.current_upload = Some(progress);
                 }
                 UploadStatus::Completed | UploadStatus::Failed(_) | UploadStatus::Cancelled => {
-                    self.upload_queue.remove(&progress.file_id);
-                    self.completed_files_count += 1;
+                    // Deferred uploads cancelled before they started were never counted
+                    if self.upload_queue.remove(&progress.file_id).is_some() {
+                        self.completed_files_count += 1;
+                    }
+                    if progress.status != UploadStatus::Completed {
+                        self.failed_uploads.retain(|p| p.file_id != progress.file_id);
+                        self.failed_uploads.insert(0, progress.clone());
+                    }
 
                     if self.upload_queue.is_empty() {
                         self.is_uploading = false;
@@ -219,15 +274,32 @@ impl DropZoneApp {
                         self.total_files_count = 0;
                         self.completed_files_count = 0;
                         self.app_state.upload_manager.reset_cancel();
-                        if let Ok(mut tray) = self.app_state.tray_manager.lock() {
-                            if let Err(e) = tray.set_icon(IconType::Normal) {
-                                tracing::error!("Failed to restore icon: {}", e);
-                            }
-                        }
                     }
                 }
             }
         }
+        drop(rx);
+
+        if received {
+            self.sync_tray_icon();
+        }
+    }
+
+    fn sync_tray_icon(&mut self) {
+        let icon = match (self.is_uploading, self.app_state.upload_manager.is_paused()) {
+            (false, _) => IconType::Normal,
+            (true, true) => IconType::Paused,
+            (true, false) => IconType::Uploading,
+        };
+        if icon == self.tray_icon {
+            return;
+        }
+        if let Ok(mut tray) = self.app_state.tray_manager.lock() {
+            match tray.set_icon(icon) {
+                Ok(()) => self.tray_icon = icon,
+                Err(e) => tracing::error!("Failed to set {:?} icon: {}", icon, e),
+            }
+        }
     }
 
     fn render_update_status(&self, ui: &mut egui::Ui) {
@@ -249,7 +321,7 @@ impl DropZoneApp {
         }
     }
 
-    fn render_drop_zone(&self, ctx: &egui::Context, ui: &mut egui::Ui) {
+    fn render_drop_zone(&mut self, ctx: &egui::Context, ui: &mut egui::Ui) {
         let is_hovering = ctx.input(|i| !i.raw.hovered_files.is_empty());
 
         if is_hovering {
@@ -268,9 +340,45 @@ impl DropZoneApp {
         });
 
         ui.add_space(20.0);
+        self.render_defer_toggle(ui);
+        self.render_link_expiry(ui);
         ui.separator();
     }
 
+    fn render_defer_toggle(&mut self, ui: &mut egui::Ui) {
+        let Ok(config) = self.app_state.config.lock() else {
+            return;
+        };
+        let window = format!(
+            "{}-{}",
+            config.deferred.window_start, config.deferred.window_end
+        );
+        drop(config);
+        ui.checkbox(&mut self.defer_drops, format!("🌙 Wyslij w oknie {window}"))
+            .on_hover_text("Upuszczone pliki czekaja w kolejce do poczatku okna");
+    }
+
+    fn render_link_expiry(&self, ui: &mut egui::Ui) {
+        let manager = &self.app_state.upload_manager;
+        if !manager.expiring_links() {
+            return;
+        }
+        let mut expiry = manager.link_expiry();
+        ui.horizontal(|ui| {
+            ui.label("🔑 Link wazny:");
+            egui::ComboBox::from_id_salt("link_expiry")
+                .selected_text(expiry_label(expiry))
+                .show_ui(ui, |ui| {
+                    for option in manager.link_expiries() {
+                        ui.selectable_value(&mut expiry, option, expiry_label(option));
+                    }
+                });
+        });
+        if expiry != manager.link_expiry() {
+            manager.set_link_expiry(expiry);
+        }
+    }
+
     fn render_upload_progress(&mut self, ui: &mut egui::Ui) {
         if !self.is_uploading || self.total_files_count == 0 {
             return;
@@ -297,7 +405,11 @@ impl DropZoneApp {
         ui.add(egui::ProgressBar::new(fraction).show_percentage());
 
         if let Some(progress) = &self.current_upload {
-            let status_text = if let Some(started) = self.upload_started_at {
+            let status_text = if progress.status == UploadStatus::WaitingForNetwork {
+                format!("{} - czeka na siec", progress.filename)
+            } else if self.app_state.upload_manager.is_paused() {
+                format!("{} - wstrzymano", progress.filename)
+            } else if let Some(started) = self.upload_started_at {
                 let elapsed = started.elapsed().as_secs_f64();
                 if elapsed > 0.5 && uploaded_bytes > 0 {
                     let speed = uploaded_bytes as f64 / elapsed;
@@ -311,14 +423,214 @@ impl DropZoneApp {
             ui.small(&status_text);
         }
 
-        if ui.small_button("Anuluj").clicked() {
-            self.app_state.upload_manager.cancel();
+        if self.upload_queue.len() > 1 {
+            let mut queued: Vec<&UploadProgress> = self.upload_queue.values().collect();
+            // Files being sent first, then the ones still waiting for a slot
+            queued.sort_by_key(|p| (p.status == UploadStatus::Queued, p.filename.as_str()));
+            for progress in queued.into_iter().take(MAX_QUEUE_ROWS) {
+                ui.horizontal(|ui| {
+                    let percent = (progress.bytes_uploaded * 100)
+                        .checked_div(progress.total_bytes)
+                        .unwrap_or(0);
+                    if progress.status == UploadStatus::WaitingForNetwork {
+                        ui.small(format!("{} - czeka na siec", progress.filename));
+                    } else {
+                        ui.small(format!("{} ({}%)", progress.filename, percent));
+                    }
+                    if progress.status == UploadStatus::Queued
+                        && ui
+                            .small_button("⏫")
+                            .on_hover_text("Przesun na poczatek kolejki")
+                            .clicked()
+                    {
+                        self.app_state.upload_manager.move_to_front(&progress.file_id);
+                    }
+                    if ui.small_button("✕").on_hover_text("Anuluj plik").clicked() {
+                        self.app_state.upload_manager.cancel_file(&progress.file_id);
+                    }
+                });
+            }
         }
 
+        let cancel_label = if self.total_files_count > 1 {
+            "Anuluj wszystkie"
+        } else {
+            "Anuluj"
+        };
+        ui.horizontal(|ui| {
+            let manager = &self.app_state.upload_manager;
+            if manager.is_paused() {
+                if ui.small_button("Wznow").clicked() {
+                    manager.resume();
+                }
+            } else if ui
+                .small_button("Wstrzymaj")
+                .on_hover_text("Rozpoczete czesci zostana dokonczone")
+                .clicked()
+            {
+                manager.pause();
+            }
+            if ui.small_button(cancel_label).clicked() {
+                manager.cancel();
+            }
+        });
+        self.render_bandwidth_limit(ui);
+        self.sync_tray_icon();
+
         ui.add_space(10.0);
         ui.separator();
     }
 
+    /// Edit the rate limit that applies right now (work hours or the default).
+    fn render_bandwidth_limit(&self, ui: &mut egui::Ui) {
+        let Ok(mut config) = self.app_state.config.lock() else {
+            return;
+        };
+        let now = chrono::Local::now().naive_local();
+
+        ui.horizontal(|ui| {
+            ui.small("Limit:");
+            let response = ui
+                .add(
+                    egui::DragValue::new(config.bandwidth.active_limit_mut(now))
+                        .range(0.0..=1000.0)
+                        .speed(0.5)
+                        .suffix(" Mb/s"),
+                )
+                .on_hover_text("0 = bez limitu");
+            if response.changed() {
+                self.app_state.upload_manager.set_bandwidth(&config.bandwidth);
+            }
+            if response.drag_stopped() || response.lost_focus() {
+                if let Err(e) = config.save(&self.app_state.config_path) {
+                    tracing::warn!("Failed to save bandwidth limit: {}", e);
+                }
+            }
+        });
+    }
+
+    fn render_deferred_uploads(&self, ui: &mut egui::Ui) {
+        let manager = &self.app_state.upload_manager;
+        let deferred = manager.deferred_uploads();
+        if deferred.is_empty() {
+            return;
+        }
+
+        ui.add_space(5.0);
+        ui.horizontal(|ui| {
+            ui.label(format!("Zaplanowane: {}", deferred.len()));
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if ui.small_button("Wyslij teraz").clicked() {
+                    manager.start_all_deferred();
+                }
+            });
+        });
+
+        for upload in deferred.iter().take(MAX_QUEUE_ROWS) {
+            ui.horizontal(|ui| {
+                ui.small(format!(
+                    "{} - {}",
+                    upload.filename,
+                    format_start_time(upload.start_at)
+                ));
+                if ui.small_button("▶").on_hover_text("Wyslij teraz").clicked() {
+                    manager.start_now(&upload.file_id);
+                }
+                if ui.small_button("✕").on_hover_text("Anuluj plik").clicked() {
+                    manager.cancel_file(&upload.file_id);
+                }
+            });
+        }
+
+        ui.separator();
+    }
+
+    fn render_failed_uploads(&mut self, ui: &mut egui::Ui) {
+        if self.failed_uploads.is_empty() {
+            return;
+        }
+
+        ui.add_space(5.0);
+        ui.horizontal(|ui| {
+            ui.label(format!("Nieudane: {}", self.failed_uploads.len()));
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if ui.small_button("Wyczysc").clicked() {
+                    for progress in self.failed_uploads.drain(..) {
+                        self.app_state.upload_manager.forget_file(&progress.file_id);
+                    }
+                }
+            });
+        });
+
+        let mut retry = None;
+        let mut dismiss = None;
+        for progress in self.failed_uploads.iter().take(MAX_QUEUE_ROWS) {
+            let reason = match &progress.status {
+                UploadStatus::Failed(msg) => msg.as_str(),
+                _ => "Anulowano",
+            };
+            ui.horizontal(|ui| {
+                ui.small(&progress.filename).on_hover_text(reason);
+                if ui.small_button("↻").on_hover_text("Ponow").clicked() {
+                    retry = Some(progress.clone());
+                }
+                if ui.small_button("✕").on_hover_text("Usun z listy").clicked() {
+                    dismiss = Some(progress.file_id.clone());
+                }
+            });
+        }
+
+        if let Some(progress) = retry {
+            self.failed_uploads.retain(|p| p.file_id != progress.file_id);
+            self.spawn_retry(progress);
+        }
+        if let Some(file_id) = dismiss {
+            self.failed_uploads.retain(|p| p.file_id != file_id);
+            self.app_state.upload_manager.forget_file(&file_id);
+        }
+
+        ui.separator();
+    }
+
+    fn render_batch_result(&mut self, ui: &mut egui::Ui) {
+        let Ok(mut batch) = self.last_batch.lock() else {
+            return;
+        };
+        let Some(result) = batch.as_ref() else {
+            return;
+        };
+
+        let mut dismiss = false;
+        ui.add_space(5.0);
+        ui.horizontal(|ui| {
+            let total = result.urls.len() + result.failed + result.cancelled;
+            ui.small(format!("Przeslano {}/{} plikow", result.urls.len(), total));
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if ui.small_button("✕").on_hover_text("Ukryj").clicked() {
+                    dismiss = true;
+                }
+                if !result.urls.is_empty()
+                    && ui
+                        .small_button("Kopiuj linki")
+                        .on_hover_text("Kopiuj linki wszystkich przeslanych plikow")
+                        .clicked()
+                {
+                    if let Ok(mut clipboard) = arboard::Clipboard::new() {
+                        if clipboard.set_text(result.urls.join("\n")).is_ok() {
+                            self.copy_feedback =
+                                Some((format!("{} linkow", result.urls.len()), Instant::now()));
+                        }
+                    }
+                }
+            });
+        });
+
+        if dismiss {
+            *batch = None;
+        }
+        ui.separator();
+    }
+
     fn render_history(&mut self, ui: &mut egui::Ui) {
         ui.add_space(10.0);
 
@@ -358,10 +670,14 @@ impl DropZoneApp {
         if entries.is_empty() {
             ui.small("Brak plikow");
         } else {
+            let expiring_links = self.app_state.upload_manager.expiring_links();
             for entry in entries.iter().take(5) {
                 let age = chrono::Utc::now().signed_duration_since(entry.timestamp);
                 let is_fresh = age.num_seconds() < 30;
                 let mut url_display = format_url_short(&entry.url, &entry.filename);
+                if entry.is_expired() {
+                    url_display = format!("⌛ {url_display}");
+                }
 
                 ui.horizontal(|ui| {
                     let available = ui.available_width() - 30.0;
@@ -380,6 +696,24 @@ impl DropZoneApp {
                         ui.add_sized([available, 18.0], text_edit)
                     };
 
+                    let mut notes = Vec::new();
+                    if entry.dedup_hit {
+                        notes.push("Plik byl juz przeslany - uzyto istniejacego linku".to_string());
+                    }
+                    if let Some(expires_at) = entry.expires_at {
+                        let at = format_expiry(expires_at);
+                        notes.push(if entry.is_expired() {
+                            format!("Link wygasl {at} - prawy klik, aby odnowic")
+                        } else {
+                            format!("Link wazny do {at}")
+                        });
+                    }
+                    let response = if notes.is_empty() {
+                        response
+                    } else {
+                        response.on_hover_text(notes.join("\n"))
+                    };
+
                     if response.clicked() {
                         if let Ok(mut clipboard) = arboard::Clipboard::new() {
                             if clipboard.set_text(entry.url.clone()).is_ok() {
@@ -392,6 +726,35 @@ impl DropZoneApp {
                             tracing::error!("Failed to open URL: {}", e);
                         }
                     }
+                    let renewable_key = entry.key.as_ref().filter(|_| expiring_links);
+                    let revocable_par = entry.par_id.as_ref().filter(|_| !entry.is_expired());
+                    if entry.direct_url.is_some()
+                        || renewable_key.is_some()
+                        || revocable_par.is_some()
+                    {
+                        response.context_menu(|ui| {
+                            if let Some(direct_url) = &entry.direct_url {
+                                if ui.button("Kopiuj link bezposredni").clicked() {
+                                    if let Ok(mut clipboard) = arboard::Clipboard::new() {
+                                        if clipboard.set_text(direct_url.clone()).is_ok() {
+                                            self.copy_feedback =
+                                                Some((entry.filename.clone(), Instant::now()));
+                                        }
+                                    }
+                                }
+                            }
+                            if let Some(key) = renewable_key {
+                                if ui.button("Odnow link").clicked() {
+                                    self.spawn_renew_link(key.clone(), entry.filename.clone());
+                                }
+                            }
+                            if let Some(par_id) = revocable_par {
+                                if ui.button("Uniewaznij link").clicked() {
+                                    self.spawn_revoke_par(par_id.clone(), entry.filename.clone());
+                                }
+                            }
+                        });
+                    }
 
                     if ui.small_button("📋").on_hover_text("Kopiuj").clicked() {
                         if let Ok(mut clipboard) = arboard::Clipboard::new() {
@@ -405,6 +768,46 @@ impl DropZoneApp {
         }
     }
 
+    fn render_active_pars(&self, ui: &mut egui::Ui) {
+        if !self.app_state.upload_manager.par_links() {
+            return;
+        }
+
+        ui.add_space(5.0);
+        egui::CollapsingHeader::new("Aktywne linki PAR")
+            .id_salt("active_pars")
+            .show(ui, |ui| {
+                if ui.small_button("Odswiez").clicked() {
+                    self.spawn_load_pars();
+                }
+                let pars = self.active_pars.lock().ok().and_then(|pars| pars.clone());
+                match pars {
+                    None => {
+                        ui.small("Odswiez, aby pobrac liste");
+                    }
+                    Some(pars) if pars.is_empty() => {
+                        ui.small("Brak aktywnych linkow");
+                    }
+                    Some(pars) => {
+                        for par in &pars {
+                            let name = par.object_name.rsplit('/').next().unwrap_or_default();
+                            ui.horizontal(|ui| {
+                                ui.small(format!("{name} - do {}", format_expiry(par.expires_at)))
+                                    .on_hover_text(&par.object_name);
+                                if ui
+                                    .small_button("✖")
+                                    .on_hover_text("Uniewaznij link")
+                                    .clicked()
+                                {
+                                    self.spawn_revoke_par(par.id.clone(), name.to_string());
+                                }
+                            });
+                        }
+                    }
+                }
+            });
+    }
+
     fn render_feedback(&mut self, ui: &mut egui::Ui) {
         if let Some((filename, instant)) = &self.copy_feedback {
             if instant.elapsed() < Duration::from_secs(2) {
@@ -438,16 +841,49 @@ impl DropZoneApp {
     }
 
     fn handle_dropped_files(&self, ctx: &egui::Context) {
-        let dropped_files: Vec<PathBuf> = ctx.input(|i| {
-            i.raw
+        let (dropped_paths, shift_held): (Vec<PathBuf>, bool) = ctx.input(|i| {
+            let paths = i
+                .raw
                 .dropped_files
                 .iter()
-                .filter_map(|f| f.path.as_ref())
-                .filter(|p| p.is_file())
-                .cloned()
-                .collect()
+                .filter_map(|f| f.path.clone())
+                .filter(|p| p.is_file() || p.is_dir())
+                .collect();
+            (paths, i.modifiers.shift)
         });
 
+        if dropped_paths.is_empty() {
+            return;
+        }
+
+        let configured_mode = self
+            .app_state
+            .config
+            .lock()
+            .map(|c| c.app.drop_mode)
+            .unwrap_or_default();
+        let drop_mode = if shift_held {
+            configured_mode.toggled()
+        } else {
+            configured_mode
+        };
+        let start_at = self
+            .defer_drops
+            .then(|| self.app_state.upload_manager.deferred_start());
+        // A single file is never worth wrapping in an archive
+        let is_single_file = dropped_paths.len() == 1 && dropped_paths[0].is_file();
+        if drop_mode == DropMode::Zip && !is_single_file {
+            self.spawn_archive_upload(dropped_paths, start_at);
+            return;
+        }
+
+        let (dropped_folders, dropped_files): (Vec<PathBuf>, Vec<PathBuf>) =
+            dropped_paths.into_iter().partition(|p| p.is_dir());
+
+        for folder in dropped_folders {
+            self.spawn_folder_upload(folder, start_at);
+        }
+
         if dropped_files.is_empty() {
             return;
         }
@@ -457,22 +893,90 @@ impl DropZoneApp {
         let history = self.app_state.history.clone();
         let error_state = self.last_error.clone();
 
+        let batch_state = self.last_batch.clone();
+
+        self.app_state.rt_handle.spawn(async move {
+            let report = match start_at {
+                Some(start_at) => manager.upload_files_deferred(dropped_files, start_at).await,
+                None => manager.upload_files(dropped_files).await,
+            };
+            let result = BatchResult::from_report(&report);
+            tracing::info!(
+                "Upload finished: {} uploaded, {} failed, {} cancelled",
+                result.urls.len(),
+                result.failed,
+                result.cancelled
+            );
+            for (filename, object) in report.uploaded() {
+                tracing::info!("  - {}", object.url);
+                history.add_entry(object.history_entry(filename));
+            }
+            for (filename, error) in report.failed() {
+                tracing::error!("Upload of {} failed: {}", filename, error);
+            }
+            // Links of deferred uploads land in history without taking over the clipboard
+            if let Some((_, first)) = report.uploaded().next().filter(|_| start_at.is_none()) {
+                if let Ok(mut clipboard) = arboard::Clipboard::new() {
+                    let _ = clipboard.set_text(first.url.clone());
+                }
+            }
+            if result.urls.is_empty() && result.failed > 0 {
+                if let Ok(mut err) = error_state.lock() {
+                    *err = Some(("Upload failed".to_string(), Instant::now()));
+                }
+            }
+            if report.files.len() > 1 {
+                if let Ok(mut batch) = batch_state.lock() {
+                    *batch = Some(result);
+                }
+            }
+        });
+    }
+
+    fn spawn_archive_upload(&self, paths: Vec<PathBuf>, start_at: Option<DateTime<Utc>>) {
+        tracing::info!("Archiving {} dropped items", paths.len());
+        let manager = self.app_state.upload_manager.clone();
+        let history = self.app_state.history.clone();
+        let error_state = self.last_error.clone();
+
         self.app_state.rt_handle.spawn(async move {
-            match manager.upload_files(dropped_files).await {
-                Ok(results) => {
-                    tracing::info!("Upload completed: {} files", results.len());
-                    for (filename, url) in &results {
-                        tracing::info!("  - {}", url);
-                        history.add(filename, url);
-                    }
-                    if let Some((_, first_url)) = results.first() {
+            match manager.upload_archive(paths, start_at).await {
+                Ok((archive_name, object)) => {
+                    tracing::info!("Archive uploaded: {}", object.url);
+                    history.add_entry(object.history_entry(&archive_name));
+                    if start_at.is_none() {
                         if let Ok(mut clipboard) = arboard::Clipboard::new() {
-                            let _ = clipboard.set_text(first_url.clone());
+                            let _ = clipboard.set_text(object.url.clone());
                         }
                     }
                 }
                 Err(e) => {
-                    tracing::error!("Upload failed: {}", e);
+                    tracing::error!("Archive upload failed: {}", e);
+                    if let Ok(mut err) = error_state.lock() {
+                        *err = Some((format!("Archive upload failed: {e}"), Instant::now()));
+                    }
+                }
+            }
+        });
+    }
+
+    fn spawn_retry(&self, progress: UploadProgress) {
+        tracing::info!("Retrying upload of {}", progress.filename);
+        let manager = self.app_state.upload_manager.clone();
+        let history = self.app_state.history.clone();
+        let error_state = self.last_error.clone();
+
+        self.app_state.rt_handle.spawn(async move {
+            match manager.retry_file(&progress.file_id).await {
+                Ok((filename, object)) => {
+                    tracing::info!("Retry succeeded: {}", object.url);
+                    history.add_entry(object.history_entry(&filename));
+                    if let Ok(mut clipboard) = arboard::Clipboard::new() {
+                        let _ = clipboard.set_text(object.url.clone());
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("Retry of {} failed: {}", progress.filename, e);
                     if let Ok(mut err) = error_state.lock() {
                         *err = Some((format!("Upload failed: {e}"), Instant::now()));
                     }
@@ -481,6 +985,123 @@ impl DropZoneApp {
         });
     }
 
+    /// Sign a new link for an object in history and copy it.
+    fn spawn_renew_link(&self, key: String, filename: String) {
+        let manager = self.app_state.upload_manager.clone();
+        let history = self.app_state.history.clone();
+        let error_state = self.last_error.clone();
+
+        self.app_state.rt_handle.spawn(async move {
+            match manager.renew_link(&key, manager.link_expiry()).await {
+                Ok(link) => {
+                    tracing::info!("Renewed link of {}", key);
+                    history.update_link(
+                        &key,
+                        &link.url,
+                        Some(link.expires_at),
+                        link.par_id.as_deref(),
+                    );
+                    if let Ok(mut clipboard) = arboard::Clipboard::new() {
+                        let _ = clipboard.set_text(link.url);
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("Renewing link of {} failed: {}", filename, e);
+                    if let Ok(mut err) = error_state.lock() {
+                        *err = Some((format!("Link renewal failed: {e}"), Instant::now()));
+                    }
+                }
+            }
+        });
+    }
+
+    /// Fetch the active PARs for the list in the window.
+    fn spawn_load_pars(&self) {
+        let manager = self.app_state.upload_manager.clone();
+        let active_pars = self.active_pars.clone();
+        let error_state = self.last_error.clone();
+
+        self.app_state.rt_handle.spawn(async move {
+            match manager.list_pars().await {
+                Ok(pars) => {
+                    if let Ok(mut active) = active_pars.lock() {
+                        *active = Some(pars);
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("Listing PARs failed: {}", e);
+                    if let Ok(mut err) = error_state.lock() {
+                        *err = Some((format!("Loading links failed: {e}"), Instant::now()));
+                    }
+                }
+            }
+        });
+    }
+
+    /// Revoke a PAR and mark its links in history as expired.
+    fn spawn_revoke_par(&self, par_id: String, filename: String) {
+        let manager = self.app_state.upload_manager.clone();
+        let history = self.app_state.history.clone();
+        let active_pars = self.active_pars.clone();
+        let error_state = self.last_error.clone();
+
+        self.app_state.rt_handle.spawn(async move {
+            match manager.revoke_par(&par_id).await {
+                Ok(()) => {
+                    tracing::info!("Revoked link of {}", filename);
+                    history.expire_par(&par_id);
+                    if let Ok(mut active) = active_pars.lock() {
+                        if let Some(pars) = active.as_mut() {
+                            pars.retain(|par| par.id != par_id);
+                        }
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("Revoking link of {} failed: {}", filename, e);
+                    if let Ok(mut err) = error_state.lock() {
+                        *err = Some((format!("Link revocation failed: {e}"), Instant::now()));
+                    }
+                }
+            }
+        });
+    }
+
+    fn spawn_folder_upload(&self, folder: PathBuf, start_at: Option<DateTime<Utc>>) {
+        tracing::info!("Folder dropped: {}", folder.display());
+        let manager = self.app_state.upload_manager.clone();
+        let history = self.app_state.history.clone();
+        let error_state = self.last_error.clone();
+
+        self.app_state.rt_handle.spawn(async move {
+            match manager.upload_folder(folder, start_at).await {
+                Ok(upload) => {
+                    tracing::info!(
+                        "Folder {} uploaded: {} files, index {}",
+                        upload.name,
+                        upload.files.len(),
+                        upload.url
+                    );
+                    for (relative, object) in &upload.files {
+                        history.add_entry(object.history_entry(relative));
+                    }
+                    // Added last so the folder link is on top of the history list
+                    history.add_entry(upload.history_entry());
+                    if start_at.is_none() {
+                        if let Ok(mut clipboard) = arboard::Clipboard::new() {
+                            let _ = clipboard.set_text(upload.url.clone());
+                        }
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("Folder upload failed: {}", e);
+                    if let Ok(mut err) = error_state.lock() {
+                        *err = Some((format!("Folder upload failed: {e}"), Instant::now()));
+                    }
+                }
+            }
+        });
+    }
+
     fn handle_clipboard_paste(&self, ctx: &egui::Context) {
         let ctrl_v_pressed = ctx.input(|i| i.key_pressed(egui::Key::V) && i.modifiers.ctrl);
         if !ctrl_v_pressed {
@@ -496,25 +1117,23 @@ impl DropZoneApp {
                     let error_state = self.last_error.clone();
 
                     self.app_state.rt_handle.spawn(async move {
-                        match manager.upload_files(vec![temp_path.clone()]).await {
-                            Ok(results) => {
-                                if let Some((_, url)) = results.first() {
-                                    tracing::info!("Screenshot uploaded: {}", url);
-                                    history.add(&filename, url);
-                                    if let Ok(mut clipboard) = arboard::Clipboard::new() {
-                                        let _ = clipboard.set_text(url.clone());
-                                    }
-                                }
-                                let _ = tokio::fs::remove_file(&temp_path).await;
+                        let report = manager
+                            .upload_files_with_priority(vec![temp_path.clone()], Priority::High)
+                            .await;
+                        if let Some((_, object)) = report.uploaded().next() {
+                            tracing::info!("Screenshot uploaded: {}", object.url);
+                            history.add_entry(object.history_entry(&filename));
+                            if let Ok(mut clipboard) = arboard::Clipboard::new() {
+                                let _ = clipboard.set_text(object.url.clone());
                             }
-                            Err(e) => {
-                                tracing::error!("Screenshot upload failed: {}", e);
-                                let _ = tokio::fs::remove_file(&temp_path).await;
-                                if let Ok(mut err) = error_state.lock() {
-                                    *err = Some((format!("Screenshot failed: {e}"), Instant::now()));
-                                }
+                        }
+                        if let Some((_, e)) = report.failed().next() {
+                            tracing::error!("Screenshot upload failed: {}", e);
+                            if let Ok(mut err) = error_state.lock() {
+                                *err = Some((format!("Screenshot failed: {e}"), Instant::now()));
                             }
                         }
+                        let _ = tokio::fs::remove_file(&temp_path).await;
                     });
                 }
             }
@@ -605,6 +1224,16 @@ fn save_image_to_temp(image_data: &arboard::ImageData, filename: &str) -> Result
 
     Ok(temp_path)
 }
+/// Planned start in local time, with the date when it is not today.
+fn format_start_time(start_at: DateTime<Utc>) -> String {
+    let local = start_at.with_timezone(&chrono::Local);
+    if local.date_naive() == chrono::Local::now().date_naive() {
+        local.format("%H:%M").to_string()
+    } else {
+        local.format("%d.%m %H:%M").to_string()
+    }
+}
+
 fn format_speed(bytes_per_sec: f64) -> String {
     if bytes_per_sec >= 1_000_000.0 {
         format!("{:.1} MB/s", bytes_per_sec / 1_000_000.0)
@@ -615,6 +1244,26 @@ fn format_speed(bytes_per_sec: f64) -> String {
     }
 }
 
+fn expiry_label(expiry: LinkExpiry) -> &'static str {
+    match expiry {
+        LinkExpiry::Hour => "1 godzina",
+        LinkExpiry::Day => "1 dzien",
+        LinkExpiry::Week => "7 dni",
+        LinkExpiry::Month => "30 dni",
+        LinkExpiry::Year => "1 rok",
+    }
+}
+
+/// Local expiry time, with the year only when it is not the current one.
+fn format_expiry(expires_at: DateTime<Utc>) -> String {
+    let local = expires_at.with_timezone(&chrono::Local);
+    if local.year() == chrono::Local::now().year() {
+        local.format("%d.%m %H:%M").to_string()
+    } else {
+        local.format("%d.%m.%Y %H:%M").to_string()
+    }
+}
+
 fn format_url_short(url: &str, original_filename: &str) -> String {
     let without_protocol = url
         .strip_prefix("https://")
@@ -1,34 +1,77 @@
 use anyhow::{Context, Result};
-use s3::creds::Credentials;
-use s3::{Bucket, Region};
+use chrono::{DateTime, Utc};
+use md5::Md5;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
-use tokio::io::AsyncReadExt;
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio_util::bytes::Bytes;
+use tokio_util::io::ReaderStream;
 use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 
-use crate::config::Config;
+use crate::archive;
+use crate::concurrency::AdaptiveLimit;
+use crate::config::{
+    AdvancedConfig, BandwidthConfig, Config, DeferredConfig, LinkExpiry, StorageBackend,
+};
+use crate::dedup_index::{DedupEntry, DedupIndex};
+use crate::folder;
+use crate::history::HistoryEntry;
+use crate::local_store::LocalStore;
+use crate::network::NetworkMonitor;
+use crate::object_store::{
+    bytes_stream, expand_url_template, multipart_md5, ByteStream, CompletedPart, ObjectStore,
+    PutOptions,
+};
+use crate::oci_par::{OciParClient, Par};
+use crate::pause_gate::PauseGate;
 use crate::portable_crypto;
+use crate::rate_limit::RateLimiter;
+use crate::s3_store::S3Store;
+use crate::scheduler::{Priority, Scheduler};
+use crate::stall::{self, StallTimer};
+use crate::upload_error::UploadError;
+use crate::upload_journal::{JournalEntry, UploadJournal};
+use crate::upload_queue::{QueueEntry, QueueState, UploadQueue};
+use futures::stream::{FuturesUnordered, StreamExt, TryStreamExt};
 
 /// RAII guard for multipart upload cleanup.
 /// Ensures `abort_upload` is called if upload is not completed (e.g., on panic).
-struct MultipartUploadGuard<'a> {
-    bucket: &'a s3::Bucket,
+/// Resumable uploads are tracked in the journal and left open for a later attempt.
+struct MultipartUploadGuard {
+    store: Arc<dyn ObjectStore>,
     s3_path: String,
     upload_id: String,
     completed: bool,
+    resumable: bool,
 }
 
-impl<'a> MultipartUploadGuard<'a> {
-    fn new(bucket: &'a s3::Bucket, s3_path: String, upload_id: String) -> Self {
+impl MultipartUploadGuard {
+    fn new(
+        store: Arc<dyn ObjectStore>,
+        s3_path: String,
+        upload_id: String,
+        resumable: bool,
+    ) -> Self {
         Self {
-            bucket,
+            store,
             s3_path,
             upload_id,
             completed: false,
+            resumable,
         }
     }
 
+    /// Give up on a resumable upload after a permanent failure, so Drop aborts it.
+    fn abandon(&mut self) {
+        self.resumable = false;
+    }
+
     /// Mark upload as completed. Drop will NOT abort.
     fn complete(mut self) {
         self.completed = true;
@@ -36,15 +79,21 @@ impl<'a> MultipartUploadGuard<'a> {
     }
 }
 
-impl Drop for MultipartUploadGuard<'_> {
+impl Drop for MultipartUploadGuard {
     fn drop(&mut self) {
-        if !self.completed {
+        if !self.completed && self.resumable {
+            tracing::info!(
+                s3_path = %self.s3_path,
+                upload_id = %self.upload_id,
+                "Multipart upload interrupted, kept for resume"
+            );
+        } else if !self.completed {
             tracing::warn!(
                 s3_path = %self.s3_path,
                 upload_id = %self.upload_id,
                 "Multipart upload not completed, aborting"
             );
-            let bucket = self.bucket.clone();
+            let store = self.store.clone();
             let s3_path = self.s3_path.clone();
             let upload_id = self.upload_id.clone();
 
@@ -56,7 +105,7 @@ impl Drop for MultipartUploadGuard<'_> {
                     let _ = rt.block_on(async {
                         tokio::time::timeout(
                             std::time::Duration::from_secs(5),
-                            bucket.abort_upload(&s3_path, &upload_id),
+                            store.abort_multipart(&s3_path, &upload_id),
                         )
                         .await
                     });
@@ -66,14 +115,396 @@ impl Drop for MultipartUploadGuard<'_> {
     }
 }
 
+/// Memory shared by the chunk buffers of all multipart uploads, counted in MiB.
+/// A buffer holds its share for as long as it is allocated, also while a pool
+/// keeps it for reuse.
+#[derive(Clone)]
+struct BufferBudget {
+    permits: Arc<tokio::sync::Semaphore>,
+    total_mb: u32,
+}
+
+impl BufferBudget {
+    fn new(total_mb: u32) -> Self {
+        let total_mb = total_mb.max(1);
+        Self {
+            permits: Arc::new(tokio::sync::Semaphore::new(total_mb as usize)),
+            total_mb,
+        }
+    }
+
+    /// Wait until `bytes` fit in the budget. A chunk larger than the whole budget
+    /// takes all of it and is sent while no other buffer is handed out.
+    async fn reserve(&self, bytes: usize) -> tokio::sync::OwnedSemaphorePermit {
+        self.permits
+            .clone()
+            .acquire_many_owned(self.share_mb(bytes))
+            .await
+            .expect("buffer budget semaphore is never closed")
+    }
+
+    /// Reserve `bytes` only if they fit right now.
+    fn try_reserve(&self, bytes: usize) -> Option<tokio::sync::OwnedSemaphorePermit> {
+        self.permits
+            .clone()
+            .try_acquire_many_owned(self.share_mb(bytes))
+            .ok()
+    }
+
+    fn share_mb(&self, bytes: usize) -> u32 {
+        (bytes as u64).div_ceil(MIB).clamp(1, u64::from(self.total_mb)) as u32
+    }
+}
+
+/// Bounded pool of reusable chunk buffers for multipart uploads.
+/// At most `capacity` buffers are handed out at once, so memory stays capped
+/// at roughly `capacity * chunk_size` regardless of file size. With a budget,
+/// buffers of all pools together, handed out or kept for reuse, stay within it.
+struct ChunkPool {
+    buffers: std::sync::Mutex<Vec<IdleBuffer>>,
+    permits: tokio::sync::Semaphore,
+    chunk_size: usize,
+    budget: Option<BufferBudget>,
+}
+
+impl ChunkPool {
+    fn new(capacity: usize, chunk_size: usize) -> Self {
+        Self {
+            buffers: std::sync::Mutex::new(Vec::with_capacity(capacity)),
+            permits: tokio::sync::Semaphore::new(capacity),
+            chunk_size,
+            budget: None,
+        }
+    }
+
+    fn with_budget(mut self, budget: &BufferBudget) -> Self {
+        self.budget = Some(budget.clone());
+        self
+    }
+
+    /// Wait for a free buffer. Reuses a returned buffer when one is available,
+    /// which already holds its share of the budget.
+    async fn acquire(&self) -> PooledChunk<'_> {
+        let permit = self
+            .permits
+            .acquire()
+            .await
+            .expect("chunk pool semaphore is never closed");
+        if let Some(idle) = self.pop_idle() {
+            return self.hand_out(permit, idle);
+        }
+        let reserved = match &self.budget {
+            Some(budget) => Some(budget.reserve(self.chunk_size).await),
+            None => None,
+        };
+        self.hand_out(permit, self.new_buffer(reserved))
+    }
+
+    /// A free buffer if one is available without waiting.
+    fn try_acquire(&self) -> Option<PooledChunk<'_>> {
+        let permit = self.permits.try_acquire().ok()?;
+        if let Some(idle) = self.pop_idle() {
+            return Some(self.hand_out(permit, idle));
+        }
+        let reserved = match &self.budget {
+            Some(budget) => Some(budget.try_reserve(self.chunk_size)?),
+            None => None,
+        };
+        Some(self.hand_out(permit, self.new_buffer(reserved)))
+    }
+
+    /// Free the buffers kept for reuse and their share of the budget, so other
+    /// uploads can use it while this one waits.
+    fn release_idle(&self) {
+        if let Ok(mut buffers) = self.buffers.lock() {
+            buffers.clear();
+        }
+    }
+
+    /// Buffer for the next part of an upload. Parts of that upload still in flight
+    /// only give their buffers back when the caller polls them, so with any of them
+    /// running this does not wait and returns `None` instead.
+    async fn acquire_next(&self, parts_in_flight: bool) -> Option<PooledChunk<'_>> {
+        if parts_in_flight {
+            self.try_acquire()
+        } else {
+            Some(self.acquire().await)
+        }
+    }
+
+    fn pop_idle(&self) -> Option<IdleBuffer> {
+        self.buffers
+            .lock()
+            .ok()
+            .and_then(|mut buffers| buffers.pop())
+    }
+
+    fn new_buffer(&self, reserved: Option<tokio::sync::OwnedSemaphorePermit>) -> IdleBuffer {
+        IdleBuffer {
+            buf: Vec::with_capacity(self.chunk_size),
+            reserved,
+        }
+    }
+
+    fn hand_out<'a>(
+        &'a self,
+        permit: tokio::sync::SemaphorePermit<'a>,
+        idle: IdleBuffer,
+    ) -> PooledChunk<'a> {
+        PooledChunk {
+            pool: self,
+            buf: idle.buf,
+            _permit: permit,
+            reserved: idle.reserved,
+        }
+    }
+}
+
+/// Buffer kept by a pool for reuse, together with its share of the budget.
+struct IdleBuffer {
+    buf: Vec<u8>,
+    reserved: Option<tokio::sync::OwnedSemaphorePermit>,
+}
+
+/// Chunk buffer borrowed from `ChunkPool`, returned to the pool on drop.
+struct PooledChunk<'a> {
+    pool: &'a ChunkPool,
+    buf: Vec<u8>,
+    _permit: tokio::sync::SemaphorePermit<'a>,
+    reserved: Option<tokio::sync::OwnedSemaphorePermit>,
+}
+
+impl PooledChunk<'_> {
+    /// Fill the buffer with exactly `len` bytes from `reader`.
+    async fn fill_from<R: AsyncRead + Unpin>(
+        &mut self,
+        reader: &mut R,
+        len: usize,
+    ) -> std::io::Result<()> {
+        self.buf.resize(len, 0);
+        reader.read_exact(&mut self.buf).await?;
+        Ok(())
+    }
+
+    /// Fill the buffer with up to `len` bytes, stopping early at end of stream.
+    async fn fill_up_to<R: AsyncRead + Unpin>(
+        &mut self,
+        reader: &mut R,
+        len: usize,
+    ) -> std::io::Result<usize> {
+        self.buf.resize(len, 0);
+        let mut filled = 0;
+        while filled < len {
+            let read = reader.read(&mut self.buf[filled..]).await?;
+            if read == 0 {
+                break;
+            }
+            filled += read;
+        }
+        self.buf.truncate(filled);
+        Ok(filled)
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        &self.buf
+    }
+
+    /// Hand out the filled buffer as a request body without copying it.
+    fn take_bytes(&mut self) -> Bytes {
+        Bytes::from(std::mem::take(&mut self.buf))
+    }
+
+    /// Take back a buffer handed out by `take_bytes` for reuse. A body still shared
+    /// elsewhere is left to drop, and the pool allocates a new buffer instead.
+    fn restore(&mut self, bytes: Bytes) {
+        if let Ok(buf) = bytes.try_into_mut() {
+            self.buf = buf.into();
+        }
+    }
+}
+
+impl Drop for PooledChunk<'_> {
+    fn drop(&mut self) {
+        let mut buf = std::mem::take(&mut self.buf);
+        // A buffer that was not taken back is already freed, and so is its share
+        if buf.capacity() == 0 {
+            return;
+        }
+        buf.clear();
+        let reserved = self.reserved.take();
+        if let Ok(mut buffers) = self.pool.buffers.lock() {
+            buffers.push(IdleBuffer { buf, reserved });
+        }
+    }
+}
+
+/// Multipart upload session, either freshly initiated or resumed from the journal.
+struct MultipartSession {
+    s3_path: String,
+    upload_id: String,
+    parts: Vec<CompletedPart>,
+}
+
+/// Dropped folder uploaded under a shared prefix.
+#[derive(Debug, Clone)]
+pub struct FolderUpload {
+    pub name: String,
+    /// Link to the generated index page listing every file
+    pub url: String,
+    pub index_key: String,
+    /// When `url` stops working, for expiring links
+    pub expires_at: Option<DateTime<Utc>>,
+    /// PAR behind `url`, which can revoke it
+    pub par_id: Option<String>,
+    /// Uploaded files keyed by their path relative to the folder
+    pub files: Vec<(String, UploadedObject)>,
+}
+
+impl FolderUpload {
+    /// History entry of the folder link.
+    pub fn history_entry(&self) -> HistoryEntry {
+        HistoryEntry {
+            key: Some(self.index_key.clone()),
+            expires_at: self.expires_at,
+            par_id: self.par_id.clone(),
+            ..HistoryEntry::new(&format!("{}/", self.name), &self.url)
+        }
+    }
+}
+
+/// Checksums of a local file.
+struct FileDigest {
+    /// Hex-encoded SHA-256, stored on the object as `x-amz-meta-sha256` when known up front
+    sha256: String,
+    /// Base64-encoded MD5, compared with the MD5 the store computed
+    md5: String,
+}
+
+/// Checksums of a request body, updated as it is read from disk.
+#[derive(Default)]
+struct StreamDigest {
+    sha256: Sha256,
+    md5: Md5,
+}
+
+impl StreamDigest {
+    fn update(&mut self, data: &[u8]) {
+        self.sha256.update(data);
+        self.md5.update(data);
+    }
+
+    fn finish(self) -> FileDigest {
+        use base64::Engine;
+
+        FileDigest {
+            sha256: format!("{:x}", self.sha256.finalize()),
+            md5: base64::engine::general_purpose::STANDARD.encode(self.md5.finalize()),
+        }
+    }
+}
+
+/// Object written to the bucket and verified against the local file.
+#[derive(Debug, Clone)]
+pub struct UploadedObject {
+    pub key: String,
+    /// Link to share, see `StorageConfig::public_url`
+    pub url: String,
+    /// Link to the file itself
+    pub direct_url: String,
+    /// When `url` stops working, for expiring links
+    pub expires_at: Option<DateTime<Utc>>,
+    /// PAR behind `url`, which can revoke it
+    pub par_id: Option<String>,
+    pub size: u64,
+    pub sha256: String,
+    /// Identical content was already in the bucket, nothing was uploaded
+    pub reused: bool,
+}
+
+impl UploadedObject {
+    pub fn history_entry(&self, filename: &str) -> HistoryEntry {
+        HistoryEntry {
+            size: self.size,
+            sha256: Some(self.sha256.clone()),
+            dedup_hit: self.reused,
+            direct_url: (self.direct_url != self.url).then(|| self.direct_url.clone()),
+            key: Some(self.key.clone()),
+            expires_at: self.expires_at,
+            par_id: self.par_id.clone(),
+            ..HistoryEntry::new(filename, &self.url)
+        }
+    }
+}
+
+/// Link that stops working at `expires_at`.
+#[derive(Debug, Clone)]
+pub struct ExpiringLink {
+    pub url: String,
+    pub expires_at: DateTime<Utc>,
+    /// PAR behind the link, when it can be revoked earlier
+    pub par_id: Option<String>,
+}
+
+/// Interrupted uploads older than this are aborted instead of resumed.
+const RESUME_MAX_AGE_DAYS: i64 = 7;
+
+/// Retries of a single failed part before the whole attempt fails.
+const PART_MAX_RETRIES: u32 = 3;
+/// Base delays of the exponential backoff for part and whole-file retries.
+const PART_RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+const UPLOAD_RETRY_BASE_DELAY: Duration = Duration::from_secs(2);
+
+/// Longest sleep of a deferred upload before the wall clock is checked again,
+/// so a PC that was suspended does not start it late.
+const DEFERRED_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Read size for streamed request bodies.
+const STREAM_BUFFER_SIZE: usize = 256 * 1024;
+/// Keepalive probes let the OS notice a dead connection that sends no RST
+const TCP_KEEPALIVE: Duration = Duration::from_secs(30);
+
+const MIB: u64 = 1024 * 1024;
+/// S3 multipart limits: part count, minimum part size (except the last) and maximum part size.
+const MAX_PARTS: u64 = 10_000;
+const MIN_PART_SIZE: u64 = 5 * MIB;
+const MAX_PART_SIZE: u64 = 5 * 1024 * MIB;
+
 pub struct S3Client {
-    bucket: Box<Bucket>,
-    namespace: String,
-    region: String,
+    store: Arc<dyn ObjectStore>,
+    part_limit: AdaptiveLimit,
+    buffer_budget: BufferBudget,
+    journal: Option<Arc<UploadJournal>>,
+    dedup: Option<Arc<DedupIndex>>,
+    reuse_existing: bool,
+    pause: PauseGate,
+    limiter: Arc<RateLimiter>,
+    stall_timeout: Duration,
+    network: Arc<NetworkMonitor>,
+    /// Admin templates for the shared and the direct link, see `StorageConfig`
+    public_url_template: Option<String>,
+    direct_url_template: Option<String>,
+    /// Links are presigned instead of pointing at a public bucket
+    presigned_links: bool,
+    /// Links are OCI PARs created through this client
+    par_client: Option<OciParClient>,
 }
 
 impl S3Client {
     pub async fn new(config: &Config) -> Result<Self> {
+        if config.storage.backend == StorageBackend::Local {
+            let root = config
+                .storage
+                .local_path
+                .as_ref()
+                .context("No storage.local_path configured")?;
+            let store = LocalStore::new(root, config.storage.local_base_url.clone())?;
+            return Ok(Self::with_store(
+                config,
+                Arc::new(store),
+                http_client(config)?,
+            ));
+        }
+
         let credentials = config
             .credentials
             .as_ref()
@@ -90,147 +521,595 @@ impl S3Client {
         access_key: &str,
         secret_key: &str,
     ) -> Result<Self> {
-        let credentials = Credentials::new(
-            Some(access_key),
-            Some(secret_key),
-            None,
-            None,
-            None,
-        )
-        .context("Failed to create S3 credentials")?;
+        let http = http_client(config)?;
+        let store = S3Store::new(config, access_key, secret_key, http.clone())?;
+        let client = Self::with_store(config, Arc::new(store), http.clone());
+
+        match &config.oci_api {
+            Some(api) if config.storage.par_links => {
+                let pars =
+                    OciParClient::new(&config.oracle, api, &crate::utils::get_exe_dir(), http)?;
+                Ok(client.with_par_client(pars))
+            }
+            _ => Ok(client),
+        }
+    }
 
-        let region = Region::Custom {
-            region: config.oracle.region.clone(),
-            endpoint: config.oracle.endpoint.clone(),
-        };
+    /// Upload to `store`, with the limits and timeouts from `config`. `http` is used
+    /// to probe the endpoint while it is unreachable.
+    pub fn with_store(config: &Config, store: Arc<dyn ObjectStore>, http: reqwest::Client) -> Self {
+        Self {
+            store,
+            part_limit: AdaptiveLimit::new(
+                "Part",
+                config.advanced.parallel_parts,
+                config.advanced.max_parallel_parts,
+                config.advanced.adaptive_concurrency,
+            ),
+            buffer_budget: BufferBudget::new(config.advanced.buffer_memory_mb),
+            journal: None,
+            dedup: None,
+            reuse_existing: false,
+            pause: PauseGate::default(),
+            limiter: Arc::new(RateLimiter::new(config.bandwidth.clone())),
+            network: NetworkMonitor::new(http, &config.s3_endpoint()),
+            stall_timeout: Duration::from_secs(config.advanced.stall_timeout_secs.max(1).into()),
+            public_url_template: config.storage.public_url.clone(),
+            direct_url_template: config.storage.direct_url.clone(),
+            presigned_links: config.storage.presigned_links,
+            par_client: None,
+        }
+    }
 
-        let bucket = Bucket::new(&config.oracle.bucket, region, credentials)
-            .context("Failed to create S3 bucket")?
-            .with_path_style();
+    /// Share uploads through OCI PARs created by `client`.
+    pub fn with_par_client(mut self, client: OciParClient) -> Self {
+        self.par_client = Some(client);
+        self
+    }
 
-        Ok(Self { 
-            bucket,
-            namespace: config.oracle.namespace.clone(),
-            region: config.oracle.region.clone(),
-        })
+    /// Track multipart uploads in `journal` so they survive app restarts.
+    pub fn with_journal(mut self, journal: Arc<UploadJournal>) -> Self {
+        self.journal = Some(journal);
+        self
     }
 
-    pub async fn upload_file_with_auto_path(&self, local_path: &Path) -> Result<String> {
-        let filename = local_path
-            .file_name()
-            .and_then(|n| n.to_str())
-            .ok_or_else(|| anyhow::anyhow!("Invalid filename"))?;
+    /// Record uploaded content in `index`. With `reuse_existing` set, content already
+    /// present in the bucket is not uploaded again and its existing link is returned.
+    pub fn with_dedup_index(mut self, index: Arc<DedupIndex>, reuse_existing: bool) -> Self {
+        self.dedup = Some(index);
+        self.reuse_existing = reuse_existing;
+        self
+    }
 
-        let s3_path = generate_s3_path(filename);
+    /// Gate checked before each part. A single PUT is never paused once started.
+    pub fn pause_gate(&self) -> &PauseGate {
+        &self.pause
+    }
 
-        let content = tokio::fs::read(local_path)
-            .await
-            .with_context(|| format!("Failed to read file: {}", local_path.display()))?;
+    /// Rate limit shared by every upload of this client.
+    pub fn rate_limiter(&self) -> &RateLimiter {
+        &self.limiter
+    }
 
-        let content_type = mime_guess::from_path(local_path)
-            .first_or_octet_stream()
-            .to_string();
+    /// Reachability of the endpoint, probed while it is down.
+    pub fn network(&self) -> &NetworkMonitor {
+        &self.network
+    }
 
-        self.bucket
-            .put_object_with_content_type(&s3_path, &content, &content_type)
+    /// Single PUT streamed from disk. The body counts bytes as they are handed to the
+    /// connection and reports them through `on_progress`, and is hashed on the way,
+    /// so the file is read once. A `digest` known up front is also sent as
+    /// `Content-MD5` and checksum metadata.
+    async fn upload_file_single_put<F>(
+        &self,
+        local_path: &Path,
+        s3_path: &str,
+        digest: Option<FileDigest>,
+        mut on_progress: F,
+    ) -> Result<UploadedObject>
+    where
+        F: FnMut(u64, u64),
+    {
+        let file = tokio::fs::File::open(local_path)
             .await
-            .context("Upload failed")?;
+            .with_context(|| format!("Failed to open file: {}", local_path.display()))?;
+        let file_size = file.metadata().await?.len();
 
-        let url = self.get_public_url(&s3_path);
-        Ok(url)
+        let options = PutOptions {
+            content_type: mime_guess::from_path(local_path)
+                .first_or_octet_stream()
+                .to_string(),
+            md5: digest.as_ref().map(|digest| digest.md5.clone()),
+            sha256: digest.map(|digest| digest.sha256),
+        };
+
+        let (sent_tx, mut sent_rx) = tokio::sync::watch::channel(0u64);
+        let timer = Arc::new(StallTimer::default());
+        let streamed = Arc::new(std::sync::Mutex::new(StreamDigest::default()));
+        let body = progress_body(
+            file,
+            sent_tx,
+            streamed.clone(),
+            self.limiter.clone(),
+            timer.clone(),
+        );
+        let request = stall::watch(
+            async {
+                self.store
+                    .put(s3_path, body, file_size, &options)
+                    .await
+                    .context("Upload failed")
+            },
+            &timer,
+            self.stall_timeout,
+        );
+        tokio::pin!(request);
+
+        let stored_md5 = loop {
+            tokio::select! {
+                result = &mut request => break result?,
+                Ok(()) = sent_rx.changed() => {
+                    let sent = *sent_rx.borrow_and_update();
+                    on_progress(sent, file_size);
+                }
+            }
+        };
+        let digest = streamed
+            .lock()
+            .map(|mut streamed| std::mem::take(&mut *streamed).finish())
+            .map_err(|_| anyhow::anyhow!("Checksum state poisoned"))?;
+
+        let object = UploadedObject {
+            key: s3_path.to_string(),
+            url: self.get_public_url(s3_path),
+            direct_url: self.get_direct_url(s3_path),
+            expires_at: None,
+            par_id: None,
+            size: file_size,
+            sha256: digest.sha256,
+            reused: false,
+        };
+        self.verify_upload(&object, Some(&digest.md5), stored_md5.as_deref())
+            .await?;
+        Ok(object)
     }
 
-    pub async fn upload_file_multipart_with_progress<P, F>(
+    /// Multipart upload of `file_path` to `s3_path`. A journaled session for the same
+    /// unchanged file is resumed under its original key instead.
+    /// Every part carries its own signed `Content-MD5`, so a corrupted part
+    /// is rejected by the server and retried like any other failed part.
+    /// The file is read once, in order, and hashed on the way; parts a resumed
+    /// session already sent are read for the checksums only.
+    async fn upload_file_multipart_with_progress<P, F>(
         &self,
         file_path: P,
+        s3_path: &str,
         chunk_size_mb: u32,
+        digest: Option<FileDigest>,
         mut on_progress: F,
-    ) -> Result<String>
+    ) -> Result<UploadedObject>
     where
         P: AsRef<Path>,
         F: FnMut(u64, u64),
     {
         let path = file_path.as_ref();
-        let filename = path
-            .file_name()
-            .and_then(|n| n.to_str())
-            .ok_or_else(|| anyhow::anyhow!("Invalid filename"))?;
-
-        let s3_path = generate_s3_path(filename);
 
         // Open file for streaming (no full file in RAM)
         let mut file = tokio::fs::File::open(path)
             .await
             .with_context(|| format!("Failed to open file: {}", path.display()))?;
 
-        let file_size = file.metadata().await?.len();
+        let metadata = file.metadata().await?;
+        let file_size = metadata.len();
+        let modified: DateTime<Utc> = metadata
+            .modified()
+            .context("Failed to read file modification time")?
+            .into();
         let content_type = mime_guess::from_path(path)
             .first_or_octet_stream()
             .to_string();
 
-        let chunk_size_bytes = (chunk_size_mb as usize) * 1024 * 1024;
+        let chunk_size_bytes = effective_chunk_size(file_size, chunk_size_mb)? as usize;
         let num_parts = (file_size as usize).div_ceil(chunk_size_bytes) as u32;
+        tracing::debug!(file_size, chunk_size_bytes, num_parts, "Multipart layout");
+
+        let session = match self
+            .resume_session(path, file_size, modified, chunk_size_bytes as u64)
+            .await
+        {
+            Some(session) => session,
+            None => {
+                self.start_session(
+                    path,
+                    s3_path,
+                    file_size,
+                    modified,
+                    chunk_size_bytes as u64,
+                    &content_type,
+                    digest.map(|digest| digest.sha256),
+                )
+                .await?
+            }
+        };
+
+        let s3_path = &session.s3_path;
+        let upload_id = &session.upload_id;
+
+        let mut guard = MultipartUploadGuard::new(
+            self.store.clone(),
+            s3_path.clone(),
+            upload_id.clone(),
+            self.journal.is_some(),
+        );
+
+        let completed_parts: HashSet<u32> =
+            session.parts.iter().map(|part| part.part_number).collect();
+        let mut uploaded_bytes: u64 = completed_parts
+            .iter()
+            .map(|&part_number| part_size(file_size, chunk_size_bytes as u64, part_number))
+            .sum();
+        if uploaded_bytes > 0 {
+            on_progress(uploaded_bytes, file_size);
+        }
+
+        // Parts are read sequentially but sent concurrently. The pool holds one
+        // buffer per in-flight part, so reading stops once all of them are busy
+        // and resumes when a finished part is collected below.
+        let pool = ChunkPool::new(self.part_limit.ceiling(), chunk_size_bytes)
+            .with_budget(&self.buffer_budget);
+        let mut in_flight = FuturesUnordered::new();
+        let mut etags = session.parts.clone();
+        let mut sha256 = Sha256::new();
+        let mut part_md5s = Vec::with_capacity(num_parts as usize);
+        let mut next_parts = (1..=num_parts).peekable();
+
+        while next_parts.peek().is_some() || !in_flight.is_empty() {
+            while !self.pause.is_paused() && in_flight.len() < self.part_limit.limit() {
+                if next_parts.peek().is_none() {
+                    break;
+                }
+                let Some(mut chunk) = pool.acquire_next(!in_flight.is_empty()).await else {
+                    break;
+                };
+                let Some(part_number) = next_parts.next() else {
+                    break;
+                };
+                let this_chunk_size = part_size(file_size, chunk_size_bytes as u64, part_number);
+
+                if let Err(e) = chunk.fill_from(&mut file, this_chunk_size as usize).await {
+                    let e = anyhow::Error::new(e)
+                        .context(format!("Failed to read chunk {part_number} from file"));
+                    self.settle_failed_session(&mut guard, &e);
+                    return Err(e);
+                }
+                sha256.update(chunk.as_slice());
+                let md5 = base64_md5(chunk.as_slice());
+                part_md5s.push(md5.clone());
+                if completed_parts.contains(&part_number) {
+                    continue;
+                }
+
+                in_flight.push(async move {
+                    let body = chunk.take_bytes();
+                    let result = self
+                        .put_part_with_retry(s3_path, upload_id, part_number, &body, &md5)
+                        .await;
+                    chunk.restore(body);
+                    (part_number, this_chunk_size, result)
+                });
+            }
+
+            // Paused with every started part finished: the session stays open until resumed
+            if in_flight.is_empty() {
+                pool.release_idle();
+                self.pause.wait_resumed().await;
+                continue;
+            }
+
+            if let Some((part_number, part_size, result)) = in_flight.next().await {
+                let part = match result {
+                    Ok(part) => part,
+                    Err(e) => {
+                        self.settle_failed_session(&mut guard, &e);
+                        return Err(e.context(format!("Failed to upload part {part_number}")));
+                    }
+                };
+                if let Some(journal) = &self.journal {
+                    journal.record_part(upload_id, part.part_number, &part.etag);
+                }
+                uploaded_bytes += part_size;
+                on_progress(uploaded_bytes, file_size);
+                etags.push(part);
+            }
+        }
+
+        // Parts finish out of order; CompleteMultipartUpload requires ascending order
+        etags.sort_by_key(|part| part.part_number);
+
+        let stored_md5 = match self
+            .store
+            .complete_multipart(s3_path, upload_id, etags)
+            .await
+        {
+            Ok(stored_md5) => stored_md5,
+            Err(e) => {
+                self.settle_failed_session(&mut guard, &e);
+                return Err(e.context("Failed to complete multipart upload"));
+            }
+        };
+
+        guard.complete();
+        self.forget_session(upload_id);
+
+        let object = UploadedObject {
+            key: s3_path.clone(),
+            url: self.get_public_url(s3_path),
+            direct_url: self.get_direct_url(s3_path),
+            expires_at: None,
+            par_id: None,
+            size: file_size,
+            sha256: format!("{:x}", sha256.finalize()),
+            reused: false,
+        };
+        let expected_md5 = multipart_md5(&part_md5s);
+        self.verify_upload(&object, expected_md5.as_deref(), stored_md5.as_deref())
+            .await?;
+        Ok(object)
+    }
+
+    /// Continue a journaled upload of `path` if the file is unchanged since it started.
+    /// A stale session is aborted so the caller starts over.
+    async fn resume_session(
+        &self,
+        path: &Path,
+        file_size: u64,
+        modified: DateTime<Utc>,
+        chunk_size: u64,
+    ) -> Option<MultipartSession> {
+        let journal = self.journal.as_ref()?;
+        let entry = journal.find_by_source(path)?;
+
+        if entry.matches_source(file_size, modified) && entry.chunk_size == chunk_size {
+            tracing::info!(
+                s3_path = %entry.s3_path,
+                upload_id = %entry.upload_id,
+                parts = entry.parts.len(),
+                "Resuming multipart upload"
+            );
+            return Some(MultipartSession {
+                s3_path: entry.s3_path,
+                upload_id: entry.upload_id,
+                parts: entry
+                    .parts
+                    .into_iter()
+                    .map(|part| CompletedPart {
+                        part_number: part.part_number,
+                        etag: part.etag,
+                    })
+                    .collect(),
+            });
+        }
+
+        tracing::info!(
+            path = %path.display(),
+            upload_id = %entry.upload_id,
+            "Source file changed since upload started, starting over"
+        );
+        self.abort_journaled(&entry).await;
+        None
+    }
 
-        let msg = self
-            .bucket
-            .initiate_multipart_upload(&s3_path, &content_type)
+    #[allow(clippy::too_many_arguments)]
+    async fn start_session(
+        &self,
+        path: &Path,
+        s3_path: &str,
+        file_size: u64,
+        modified: DateTime<Utc>,
+        chunk_size: u64,
+        content_type: &str,
+        sha256: Option<String>,
+    ) -> Result<MultipartSession> {
+        let upload_id = self
+            .store
+            .initiate_multipart(
+                s3_path,
+                &PutOptions {
+                    content_type: content_type.to_string(),
+                    md5: None,
+                    sha256: sha256.clone(),
+                },
+            )
             .await
             .context("Failed to initiate multipart upload")?;
 
-        let upload_id = &msg.upload_id;
+        if let Some(journal) = &self.journal {
+            journal.begin(JournalEntry {
+                upload_id: upload_id.clone(),
+                s3_path: s3_path.to_string(),
+                source_path: path.to_path_buf(),
+                file_size,
+                modified,
+                chunk_size,
+                started_at: Utc::now(),
+                sha256,
+                parts: Vec::new(),
+            });
+        }
 
-        let guard = MultipartUploadGuard::new(&self.bucket, s3_path.clone(), upload_id.clone());
+        Ok(MultipartSession {
+            s3_path: s3_path.to_string(),
+            upload_id,
+            parts: Vec::new(),
+        })
+    }
 
-        let mut etags = Vec::new();
-        let mut uploaded_bytes: u64 = 0;
+    /// Decide what happens to a journaled session after `error`. Interruptions keep
+    /// it for resume, a lost session is forgotten, and a permanent failure is also
+    /// aborted on drop since resuming would fail the same way.
+    fn settle_failed_session(&self, guard: &mut MultipartUploadGuard, error: &anyhow::Error) {
+        if is_session_lost(error) {
+            // Nothing left to resume, the next attempt has to start over
+            self.forget_session(&guard.upload_id);
+        } else if !UploadError::classify(error).is_retryable() {
+            self.forget_session(&guard.upload_id);
+            guard.abandon();
+        }
+    }
 
-        for part_number in 1..=num_parts {
-            let remaining = file_size - uploaded_bytes;
-            let this_chunk_size = std::cmp::min(remaining as usize, chunk_size_bytes);
+    fn forget_session(&self, upload_id: &str) {
+        if let Some(journal) = &self.journal {
+            journal.remove(upload_id);
+        }
+    }
 
-            let mut chunk = vec![0u8; this_chunk_size];
-            file.read_exact(&mut chunk)
-                .await
-                .with_context(|| format!("Failed to read chunk {part_number} from file"))?;
+    async fn abort_journaled(&self, entry: &JournalEntry) {
+        self.forget_session(&entry.upload_id);
+        if let Err(e) = self
+            .store
+            .abort_multipart(&entry.s3_path, &entry.upload_id)
+            .await
+        {
+            tracing::warn!(
+                upload_id = %entry.upload_id,
+                "Failed to abort stale multipart upload: {}",
+                e
+            );
+        }
+    }
+
+    /// Abort the journaled multipart upload of `path`, if any, so it is not resumed later.
+    pub async fn discard_resumable(&self, path: &Path) {
+        let Some(entry) = self.journal.as_ref().and_then(|j| j.find_by_source(path)) else {
+            return;
+        };
+        self.abort_journaled(&entry).await;
+    }
+
+    /// Source files of interrupted multipart uploads that can be continued.
+    /// Entries whose source is gone or that are too old to resume are aborted and dropped.
+    pub async fn resumable_sources(&self) -> Vec<PathBuf> {
+        let Some(journal) = &self.journal else {
+            return Vec::new();
+        };
 
+        let mut sources = Vec::new();
+        for entry in journal.get_all() {
+            let age = Utc::now().signed_duration_since(entry.started_at);
+            if age.num_days() >= RESUME_MAX_AGE_DAYS || !entry.source_path.is_file() {
+                tracing::info!(
+                    path = %entry.source_path.display(),
+                    "Dropping interrupted upload that can no longer be resumed"
+                );
+                self.abort_journaled(&entry).await;
+            } else {
+                sources.push(entry.source_path);
+            }
+        }
+        sources
+    }
+
+    /// Upload a part, retrying transient failures with backoff under the same upload id.
+    /// A lost session is returned immediately since retrying the part cannot succeed;
+    /// the caller starts a new session instead.
+    async fn put_part_with_retry(
+        &self,
+        s3_path: &str,
+        upload_id: &str,
+        part_number: u32,
+        content: &Bytes,
+        md5: &str,
+    ) -> Result<CompletedPart> {
+        let mut attempts = 0;
+        loop {
             match self
-                .bucket
-                .put_multipart_chunk(chunk, &s3_path, part_number, upload_id, &content_type)
+                .put_part(s3_path, upload_id, part_number, content, md5)
                 .await
             {
                 Ok(part) => {
-                    uploaded_bytes += this_chunk_size as u64;
-                    on_progress(uploaded_bytes, file_size);
-                    etags.push(s3::serde_types::Part {
-                        etag: part.etag.clone(),
-                        part_number,
-                    });
+                    self.part_limit.record(content.len() as u64, true);
+                    return Ok(part);
                 }
                 Err(e) => {
-                    // Guard will handle abort in drop
-                    return Err(anyhow::anyhow!("Failed to upload part {part_number}: {e}"));
+                    let error = UploadError::classify(&e);
+                    if error.is_retryable() {
+                        self.part_limit.record(0, false);
+                    }
+                    if error == UploadError::SessionLost
+                        || !error.is_retryable()
+                        || attempts >= PART_MAX_RETRIES
+                    {
+                        return Err(e);
+                    }
+                    attempts += 1;
+                    let delay = error.retry_delay(attempts, PART_RETRY_BASE_DELAY);
+                    tracing::warn!(
+                        part_number,
+                        attempt = attempts,
+                        "Part upload failed, retrying in {:.1}s: {}",
+                        delay.as_secs_f64(),
+                        error
+                    );
+                    tokio::time::sleep(delay).await;
                 }
             }
         }
+    }
 
-        self.bucket
-            .complete_multipart_upload(&s3_path, upload_id, etags)
-            .await
-            .context("Failed to complete multipart upload")?;
-
-        guard.complete();
+    /// Upload a single part with `md5` as its `Content-MD5`. The part is sent as a
+    /// stream of slices sharing `content`, so the body can be paced by the rate limiter
+    /// and watched for stalls, and the caller gets the buffer back for reuse once the
+    /// request is done.
+    async fn put_part(
+        &self,
+        s3_path: &str,
+        upload_id: &str,
+        part_number: u32,
+        content: &Bytes,
+        md5: &str,
+    ) -> Result<CompletedPart> {
+        let body = content.clone();
+        let slices = (0..body.len())
+            .step_by(STREAM_BUFFER_SIZE)
+            .map(move |start| Ok(body.slice(start..(start + STREAM_BUFFER_SIZE).min(body.len()))))
+            .collect::<Vec<std::io::Result<Bytes>>>();
+        let timer = Arc::new(StallTimer::default());
+        let body = throttled(
+            futures::stream::iter(slices),
+            self.limiter.clone(),
+            timer.clone(),
+        )
+        .boxed();
+        let etag = stall::watch(
+            async {
+                self.store
+                    .put_part(
+                        s3_path,
+                        upload_id,
+                        part_number,
+                        body,
+                        content.len() as u64,
+                        md5,
+                    )
+                    .await
+                    .context("Part upload failed")
+            },
+            &timer,
+            self.stall_timeout,
+        )
+        .await?;
 
-        let url = self.get_public_url(&s3_path);
-        Ok(url)
+        Ok(CompletedPart { part_number, etag })
     }
 
     pub async fn upload_file_auto_with_progress<P, F>(
         &self,
         file_path: P,
+        s3_path: &str,
         threshold_mb: u32,
         chunk_mb: u32,
         on_progress: F,
-    ) -> Result<String>
+    ) -> Result<UploadedObject>
     where
         P: AsRef<Path>,
         F: FnMut(u64, u64),
@@ -243,21 +1122,367 @@ impl S3Client {
         let size = metadata.len();
         let threshold = u64::from(threshold_mb) * 1024 * 1024;
 
-        if size >= threshold {
-            self.upload_file_multipart_with_progress(path, chunk_mb, on_progress).await
+        // Reusing links needs the hash up front for the lookup, and it is then handed on
+        // so it goes out as Content-MD5 and checksum metadata. Otherwise the file is only
+        // hashed while it is sent.
+        let digest = match &self.dedup {
+            Some(_) if self.reuse_existing => Some(hash_file(path).await?),
+            _ => None,
+        };
+        if let Some(digest) = &digest {
+            if let Some(existing) = self.find_existing(&digest.sha256, size).await {
+                return Ok(existing);
+            }
+        }
+
+        // Multipart needs at least one part, so empty files always go through a single PUT
+        let object = if size >= threshold && size > 0 {
+            self.upload_file_multipart_with_progress(path, s3_path, chunk_mb, digest, on_progress)
+                .await?
         } else {
-            self.upload_file_with_auto_path(path).await
+            self.upload_file_single_put(path, s3_path, digest, on_progress)
+                .await?
+        };
+
+        if let Some(index) = &self.dedup {
+            index.insert(DedupEntry {
+                sha256: object.sha256.clone(),
+                bucket: self.store.name(),
+                key: object.key.clone(),
+                url: object.url.clone(),
+                size: object.size,
+                uploaded_at: Utc::now(),
+            });
         }
+        Ok(object)
     }
 
-    fn get_public_url(&self, key: &str) -> String {
-        build_public_url(
-            &self.namespace,
-            &self.region,
-            &self.bucket.name(),
-            key,
-        )
-    }
+    /// Previously uploaded object with the same content, if reuse is enabled and the
+    /// object is still in the bucket unchanged. Index entries for objects that are gone
+    /// or were overwritten are dropped.
+    async fn find_existing(&self, sha256: &str, size: u64) -> Option<UploadedObject> {
+        let index = self.dedup.as_ref()?;
+        let entry = index.find(&self.store.name(), sha256)?;
+
+        match self.store.head(&entry.key).await {
+            Ok(Some(head)) => {
+                if head.size != size || head.sha256.as_deref() != Some(sha256) {
+                    tracing::info!(key = %entry.key, "Indexed object changed, uploading again");
+                    index.remove(&entry.bucket, &entry.key);
+                    return None;
+                }
+            }
+            Ok(None) => {
+                tracing::info!(key = %entry.key, "Indexed object no longer exists, uploading again");
+                index.remove(&entry.bucket, &entry.key);
+                return None;
+            }
+            Err(e) => {
+                tracing::warn!(key = %entry.key, "Failed to check indexed object: {}", e);
+                return None;
+            }
+        }
+
+        tracing::info!(key = %entry.key, "Identical content already uploaded, reusing link");
+        Some(UploadedObject {
+            direct_url: self.get_direct_url(&entry.key),
+            expires_at: None,
+            par_id: None,
+            key: entry.key,
+            url: entry.url,
+            size,
+            sha256: entry.sha256,
+            reused: true,
+        })
+    }
+
+    /// Forget index entries whose objects were deleted from the bucket, so the index
+    /// does not keep links to files that are gone. Only done while reuse is enabled,
+    /// since the index is not consulted otherwise.
+    pub async fn prune_dedup_index(&self) {
+        let Some(index) = self.dedup.as_ref().filter(|_| self.reuse_existing) else {
+            return;
+        };
+        for entry in index.entries_for(&self.store.name()) {
+            match self.store.head(&entry.key).await {
+                Ok(Some(_)) => {}
+                Ok(None) => {
+                    tracing::info!(key = %entry.key, "Indexed object no longer exists, forgetting it");
+                    index.remove(&entry.bucket, &entry.key);
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to check indexed objects: {}", e);
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Multipart upload from a stream of unknown length, such as an archive built on the fly.
+    /// Parts are read in order and sent concurrently like file uploads. The session is not
+    /// journaled since a stream cannot be replayed after a restart. `size_hint` only picks
+    /// the part size; the stream may end up smaller.
+    pub async fn upload_stream_multipart<R, F>(
+        &self,
+        mut reader: R,
+        s3_path: &str,
+        content_type: &str,
+        size_hint: u64,
+        chunk_size_mb: u32,
+        mut on_progress: F,
+    ) -> Result<UploadedObject>
+    where
+        R: AsyncRead + Unpin,
+        F: FnMut(u64),
+    {
+        let chunk_size = effective_chunk_size(size_hint, chunk_size_mb)? as usize;
+
+        let upload_id = self
+            .store
+            .initiate_multipart(
+                s3_path,
+                &PutOptions {
+                    content_type: content_type.to_string(),
+                    ..PutOptions::default()
+                },
+            )
+            .await
+            .context("Failed to initiate multipart upload")?;
+        let guard = MultipartUploadGuard::new(
+            self.store.clone(),
+            s3_path.to_string(),
+            upload_id.clone(),
+            false,
+        );
+        let upload_id = upload_id.as_str();
+
+        let pool = ChunkPool::new(self.part_limit.ceiling(), chunk_size)
+            .with_budget(&self.buffer_budget);
+        let mut in_flight = FuturesUnordered::new();
+        let mut etags = Vec::new();
+        let mut sha256 = Sha256::new();
+        let mut total_size: u64 = 0;
+        let mut uploaded_bytes: u64 = 0;
+        let mut part_number: u32 = 0;
+        let mut end_of_stream = false;
+
+        while !end_of_stream || !in_flight.is_empty() {
+            while !end_of_stream
+                && !self.pause.is_paused()
+                && in_flight.len() < self.part_limit.limit()
+            {
+                let Some(mut chunk) = pool.acquire_next(!in_flight.is_empty()).await else {
+                    break;
+                };
+                let read = chunk
+                    .fill_up_to(&mut reader, chunk_size)
+                    .await
+                    .context("Failed to read upload stream")?;
+                end_of_stream = read < chunk_size;
+                // A stream ending on a part boundary leaves nothing for a last part,
+                // but an empty stream still needs one part to complete
+                if read == 0 && part_number > 0 {
+                    break;
+                }
+                part_number += 1;
+                if u64::from(part_number) > MAX_PARTS {
+                    anyhow::bail!("Stream too large for multipart upload");
+                }
+                sha256.update(chunk.as_slice());
+                let md5 = base64_md5(chunk.as_slice());
+                total_size += read as u64;
+
+                let this_part = part_number;
+                in_flight.push(async move {
+                    let body = chunk.take_bytes();
+                    let result = self
+                        .put_part_with_retry(s3_path, upload_id, this_part, &body, &md5)
+                        .await;
+                    chunk.restore(body);
+                    (this_part, read as u64, result)
+                });
+            }
+
+            if in_flight.is_empty() {
+                pool.release_idle();
+                self.pause.wait_resumed().await;
+                continue;
+            }
+
+            if let Some((part_number, part_size, result)) = in_flight.next().await {
+                let part =
+                    result.map_err(|e| e.context(format!("Failed to upload part {part_number}")))?;
+                uploaded_bytes += part_size;
+                on_progress(uploaded_bytes);
+                etags.push(part);
+            }
+        }
+
+        etags.sort_by_key(|part| part.part_number);
+        self.store
+            .complete_multipart(s3_path, upload_id, etags)
+            .await
+            .context("Failed to complete multipart upload")?;
+        guard.complete();
+
+        let object = UploadedObject {
+            key: s3_path.to_string(),
+            url: self.get_public_url(s3_path),
+            direct_url: self.get_direct_url(s3_path),
+            expires_at: None,
+            par_id: None,
+            size: total_size,
+            sha256: format!("{:x}", sha256.finalize()),
+            reused: false,
+        };
+        // The checksum is only known once the stream ends, too late for initiate metadata
+        self.verify_upload(&object, None, None).await?;
+        Ok(object)
+    }
+
+    /// Compare the stored object with what was sent: its size, and the MD5 the store
+    /// computed over the bytes it received with `expected_md5`, worked out locally
+    /// while the file was read. Without an MD5 from the store only the size is checked.
+    /// A mismatching object is deleted so a broken file is never shared.
+    async fn verify_upload(
+        &self,
+        object: &UploadedObject,
+        expected_md5: Option<&str>,
+        stored_md5: Option<&str>,
+    ) -> Result<()> {
+        let remote_size = self
+            .store
+            .head(&object.key)
+            .await
+            .context("Failed to verify uploaded object")?
+            .map(|head| head.size);
+
+        let md5_matches = match (expected_md5, stored_md5) {
+            (Some(expected), Some(stored)) => expected == stored,
+            _ => true,
+        };
+        if remote_size == Some(object.size) && md5_matches {
+            return Ok(());
+        }
+
+        tracing::error!(
+            key = %object.key,
+            expected_size = object.size,
+            ?remote_size,
+            ?expected_md5,
+            ?stored_md5,
+            "Uploaded object does not match local file, deleting"
+        );
+        if let Err(e) = self.store.delete(&object.key).await {
+            tracing::warn!(key = %object.key, "Failed to delete corrupted object: {}", e);
+        }
+        Err(UploadError::Integrity(object.key.clone()).into())
+    }
+
+    /// Link handed out for `key`, from the public URL template if one is set.
+    fn get_public_url(&self, key: &str) -> String {
+        match &self.public_url_template {
+            Some(template) => expand_url_template(template, &self.store.name(), key),
+            None => self.store.public_url(key),
+        }
+    }
+
+    /// Direct download link for `key`, from the direct URL template if one is set.
+    fn get_direct_url(&self, key: &str) -> String {
+        match &self.direct_url_template {
+            Some(template) => expand_url_template(template, &self.store.name(), key),
+            None => self.store.public_url(key),
+        }
+    }
+
+    /// Whether links are presigned and expire.
+    pub fn presigned_links(&self) -> bool {
+        self.presigned_links
+    }
+
+    /// Whether links are OCI PARs, which expire and can be revoked.
+    pub fn par_links(&self) -> bool {
+        self.par_client.is_some()
+    }
+
+    /// Whether links expire, presigned or PARs.
+    pub fn expiring_links(&self) -> bool {
+        self.presigned_links || self.par_links()
+    }
+
+    /// Link to `key` that stays valid for `expiry`: a PAR when PAR links are on,
+    /// presigned otherwise.
+    async fn expiring_link(&self, key: &str, expiry: LinkExpiry) -> Result<ExpiringLink> {
+        let expires_in = expiry.duration();
+        let expires_at = Utc::now() + chrono::Duration::seconds(expires_in.as_secs() as i64);
+        if let Some(pars) = &self.par_client {
+            let created = pars.create(key, expires_at).await?;
+            return Ok(ExpiringLink {
+                url: created.url,
+                expires_at: created.par.expires_at,
+                par_id: Some(created.par.id),
+            });
+        }
+
+        let url = self.store.presign_get(key, expires_in).await?;
+        Ok(ExpiringLink {
+            url,
+            expires_at,
+            par_id: None,
+        })
+    }
+
+    /// `object` with a link valid for `expiry` when links expire, unchanged otherwise.
+    pub async fn share(
+        &self,
+        object: UploadedObject,
+        expiry: LinkExpiry,
+    ) -> Result<UploadedObject> {
+        if !self.expiring_links() {
+            return Ok(object);
+        }
+        let link = self.expiring_link(&object.key, expiry).await?;
+        Ok(UploadedObject {
+            direct_url: link.url.clone(),
+            url: link.url,
+            expires_at: Some(link.expires_at),
+            par_id: link.par_id,
+            ..object
+        })
+    }
+
+    /// New expiring link to an object uploaded earlier. Fails if it no longer exists.
+    pub async fn renew_link(&self, key: &str, expiry: LinkExpiry) -> Result<ExpiringLink> {
+        if self.store.head(key).await?.is_none() {
+            anyhow::bail!("{key} no longer exists");
+        }
+        self.expiring_link(key, expiry).await
+    }
+
+    /// Active PARs created by the app.
+    pub async fn list_pars(&self) -> Result<Vec<Par>> {
+        self.par_client()?.list().await
+    }
+
+    /// Revoke a PAR so its link stops working before it expires.
+    pub async fn revoke_par(&self, par_id: &str) -> Result<()> {
+        self.par_client()?.delete(par_id).await
+    }
+
+    fn par_client(&self) -> Result<&OciParClient> {
+        self.par_client
+            .as_ref()
+            .context("PAR links are not enabled (storage.par_links)")
+    }
+
+    /// Upload the index page of a folder upload and return its link.
+    pub async fn upload_folder_index(&self, key: &str, html: &str) -> Result<String> {
+        self.put_small(key, html.as_bytes(), "text/html; charset=utf-8")
+            .await
+            .context("Failed to upload folder index")?;
+
+        Ok(self.get_public_url(key))
+    }
 
     /// Upload robots.txt to bucket root to discourage search engine indexing.
     /// Content: "User-agent: *\nDisallow: /"
@@ -265,14 +1490,78 @@ impl S3Client {
         const ROBOTS_CONTENT: &str = "User-agent: *\nDisallow: /\n";
         const ROBOTS_KEY: &str = "robots.txt";
 
-        self.bucket
-            .put_object_with_content_type(ROBOTS_KEY, ROBOTS_CONTENT.as_bytes(), "text/plain")
+        self.put_small(ROBOTS_KEY, ROBOTS_CONTENT.as_bytes(), "text/plain")
             .await
             .context("Failed to upload robots.txt")?;
 
         let url = self.get_public_url(ROBOTS_KEY);
         Ok(url)
     }
+
+    /// Write a small object held in memory in one request.
+    async fn put_small(&self, key: &str, content: &[u8], content_type: &str) -> Result<()> {
+        let options = PutOptions {
+            content_type: content_type.to_string(),
+            md5: Some(base64_md5(content)),
+            ..PutOptions::default()
+        };
+        self.store
+            .put(
+                key,
+                bytes_stream(content.to_vec()),
+                content.len() as u64,
+                &options,
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+/// What happened to one file of a batch.
+#[derive(Debug, Clone)]
+pub enum FileOutcome {
+    Uploaded(UploadedObject),
+    Failed(String),
+    Cancelled,
+}
+
+#[derive(Debug, Clone)]
+pub struct FileReport {
+    pub file_id: String,
+    /// Name reported in progress and history
+    pub filename: String,
+    pub outcome: FileOutcome,
+}
+
+/// Per-file results of a batch, so one failed file does not hide the others.
+#[derive(Debug, Clone, Default)]
+pub struct BatchReport {
+    pub files: Vec<FileReport>,
+}
+
+impl BatchReport {
+    /// Files that made it into the bucket, with the names to record them under.
+    pub fn uploaded(&self) -> impl Iterator<Item = (&str, &UploadedObject)> {
+        self.files.iter().filter_map(|file| match &file.outcome {
+            FileOutcome::Uploaded(object) => Some((file.filename.as_str(), object)),
+            _ => None,
+        })
+    }
+
+    /// Files that failed, with their error.
+    pub fn failed(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.files.iter().filter_map(|file| match &file.outcome {
+            FileOutcome::Failed(error) => Some((file.filename.as_str(), error.as_str())),
+            _ => None,
+        })
+    }
+
+    pub fn cancelled_count(&self) -> usize {
+        self.files
+            .iter()
+            .filter(|file| matches!(file.outcome, FileOutcome::Cancelled))
+            .count()
+    }
 }
 
 /// Upload status tracking
@@ -283,11 +1572,15 @@ pub enum UploadStatus {
     Completed,
     Failed(String),
     Cancelled,
+    /// Waiting for the queue to be resumed; parts already sent are kept
+    Paused,
+    /// Held until the endpoint is reachable again; parts already sent are kept
+    WaitingForNetwork,
 }
 
 #[derive(Debug, Clone)]
 pub struct UploadProgress {
-    #[allow(dead_code)]
+    /// Stable for the whole life of a queued upload, including retries
     pub file_id: String,
     pub filename: String,
     pub bytes_uploaded: u64,
@@ -295,19 +1588,275 @@ pub struct UploadProgress {
     pub status: UploadStatus,
 }
 
+/// What a queued upload sends, kept so a failed or cancelled upload can be retried.
+#[derive(Debug, Clone)]
+enum JobSource {
+    File {
+        path: PathBuf,
+        filename: String,
+        s3_path: String,
+        /// Set for uploads restored from a previous run; the file must be unchanged
+        expected_source: Option<FileSnapshot>,
+    },
+    Archive {
+        paths: Vec<PathBuf>,
+    },
+}
+
+impl JobSource {
+    /// Name reported in progress and history.
+    fn name(&self) -> String {
+        match self {
+            JobSource::File { filename, .. } => filename.clone(),
+            JobSource::Archive { paths } => archive::archive_name(paths),
+        }
+    }
+}
+
+/// Upload waiting in the queue for its planned start.
+#[derive(Debug, Clone)]
+pub struct DeferredUpload {
+    pub file_id: String,
+    pub filename: String,
+    pub start_at: DateTime<Utc>,
+}
+
+/// Size and mtime of a source file when it was queued.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct FileSnapshot {
+    size: u64,
+    modified: DateTime<Utc>,
+}
+
+impl FileSnapshot {
+    fn of(path: &Path) -> Result<Self> {
+        let metadata = std::fs::metadata(path)
+            .with_context(|| format!("Failed to get file metadata: {}", path.display()))?;
+        Ok(Self {
+            size: metadata.len(),
+            modified: metadata
+                .modified()
+                .context("Failed to read file modification time")?
+                .into(),
+        })
+    }
+
+    /// Fail if `path` is gone or no longer has this size and mtime.
+    fn check(&self, path: &Path) -> Result<()> {
+        if Self::of(path)? != *self {
+            anyhow::bail!("{} changed since it was queued", path.display());
+        }
+        Ok(())
+    }
+}
+
+struct UploadJob {
+    source: JobSource,
+    token: CancellationToken,
+    finished: bool,
+    /// Set while a deferred job waits for its planned start
+    deferral: Option<Deferral>,
+}
+
+struct Deferral {
+    start_at: DateTime<Utc>,
+    /// Wakes the waiting job when the user starts it early
+    start_now: Arc<tokio::sync::Notify>,
+}
+
+/// Queued uploads by file id. Each job has its own token, a child of the batch
+/// token, so it can be cancelled alone or together with everything else.
+/// Successful jobs are dropped; failed and cancelled ones stay until retried or forgotten.
+#[derive(Default)]
+struct JobRegistry {
+    jobs: std::sync::Mutex<HashMap<String, UploadJob>>,
+}
+
+impl JobRegistry {
+    fn register(&self, batch: &CancellationToken, source: JobSource) -> (String, CancellationToken) {
+        let file_id = Uuid::new_v4().to_string();
+        let token = self.register_as(&file_id, batch, source);
+        (file_id, token)
+    }
+
+    /// Register a job under a known file id, e.g. one kept in the persistent queue.
+    fn register_as(
+        &self,
+        file_id: &str,
+        batch: &CancellationToken,
+        source: JobSource,
+    ) -> CancellationToken {
+        let token = batch.child_token();
+        if let Ok(mut jobs) = self.jobs.lock() {
+            jobs.insert(
+                file_id.to_string(),
+                UploadJob {
+                    source,
+                    token: token.clone(),
+                    finished: false,
+                    deferral: None,
+                },
+            );
+        }
+        token
+    }
+
+    /// Hold a registered job back until `start_at`.
+    fn defer(&self, file_id: &str, start_at: DateTime<Utc>) {
+        if let Ok(mut jobs) = self.jobs.lock() {
+            if let Some(job) = jobs.get_mut(file_id) {
+                job.deferral = Some(Deferral {
+                    start_at,
+                    start_now: Arc::new(tokio::sync::Notify::new()),
+                });
+            }
+        }
+    }
+
+    /// Planned start of a deferred job and the signal that starts it early.
+    fn deferral(&self, file_id: &str) -> Option<(DateTime<Utc>, Arc<tokio::sync::Notify>)> {
+        let jobs = self.jobs.lock().ok()?;
+        let deferral = jobs.get(file_id)?.deferral.as_ref()?;
+        Some((deferral.start_at, deferral.start_now.clone()))
+    }
+
+    /// Start a deferred job that is still waiting. Returns false if there is none.
+    fn start_now(&self, file_id: &str) -> bool {
+        let Ok(jobs) = self.jobs.lock() else {
+            return false;
+        };
+        match jobs.get(file_id) {
+            Some(UploadJob {
+                finished: false,
+                deferral: Some(deferral),
+                ..
+            }) => {
+                // Stored as a permit if the job is not waiting on it yet
+                deferral.start_now.notify_one();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn mark_started(&self, file_id: &str) {
+        if let Ok(mut jobs) = self.jobs.lock() {
+            if let Some(job) = jobs.get_mut(file_id) {
+                job.deferral = None;
+            }
+        }
+    }
+
+    /// Jobs waiting for their planned start, earliest first.
+    fn deferred(&self) -> Vec<DeferredUpload> {
+        let Ok(jobs) = self.jobs.lock() else {
+            return Vec::new();
+        };
+        let mut deferred: Vec<_> = jobs
+            .iter()
+            .filter(|(_, job)| !job.finished)
+            .filter_map(|(file_id, job)| {
+                Some(DeferredUpload {
+                    file_id: file_id.clone(),
+                    filename: job.source.name(),
+                    start_at: job.deferral.as_ref()?.start_at,
+                })
+            })
+            .collect();
+        deferred.sort_by(|a, b| (a.start_at, &a.filename).cmp(&(b.start_at, &b.filename)));
+        deferred
+    }
+
+    /// Add a job left over from a previous run, ready to be retried.
+    fn restore(&self, file_id: String, source: JobSource) {
+        if let Ok(mut jobs) = self.jobs.lock() {
+            jobs.insert(
+                file_id,
+                UploadJob {
+                    source,
+                    token: CancellationToken::new(),
+                    finished: true,
+                    deferral: None,
+                },
+            );
+        }
+    }
+
+    fn cancel(&self, file_id: &str) -> bool {
+        let Ok(jobs) = self.jobs.lock() else {
+            return false;
+        };
+        match jobs.get(file_id) {
+            Some(job) if !job.finished => {
+                job.token.cancel();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Rearm a finished job with a fresh token under `batch`.
+    fn restart(
+        &self,
+        batch: &CancellationToken,
+        file_id: &str,
+    ) -> Result<(JobSource, CancellationToken)> {
+        let mut jobs = self
+            .jobs
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Upload queue lock poisoned"))?;
+        let job = jobs
+            .get_mut(file_id)
+            .ok_or_else(|| anyhow::anyhow!("Unknown upload: {file_id}"))?;
+        if !job.finished {
+            anyhow::bail!("Upload {file_id} is still running");
+        }
+        job.finished = false;
+        job.token = batch.child_token();
+        // A retry starts right away, even if the job was deferred before
+        job.deferral = None;
+        Ok((job.source.clone(), job.token.clone()))
+    }
+
+    fn finish(&self, file_id: &str, succeeded: bool) {
+        if let Ok(mut jobs) = self.jobs.lock() {
+            if succeeded {
+                jobs.remove(file_id);
+            } else if let Some(job) = jobs.get_mut(file_id) {
+                job.finished = true;
+            }
+        }
+    }
+
+    fn forget(&self, file_id: &str) {
+        if let Ok(mut jobs) = self.jobs.lock() {
+            if jobs.get(file_id).is_some_and(|job| job.finished) {
+                jobs.remove(file_id);
+            }
+        }
+    }
+}
+
 /// Manages upload queue with parallel processing and progress tracking
 pub struct UploadManager {
     s3_client: S3Client,
-    parallel_limit: usize,
+    scheduler: Scheduler,
+    multipart_threshold_mb: u32,
+    multipart_chunk_mb: u32,
     max_retries: u32,
     progress_tx: tokio::sync::mpsc::UnboundedSender<UploadProgress>,
     cancel_token: std::sync::Mutex<CancellationToken>,
+    jobs: JobRegistry,
+    queue: Option<Arc<UploadQueue>>,
+    deferred_window: DeferredConfig,
+    /// Validity of presigned links for uploads queued from now on
+    link_expiry: std::sync::Mutex<LinkExpiry>,
 }
 
 impl UploadManager {
     pub fn new(
         s3_client: S3Client,
-        parallel_limit: usize,
+        advanced: &AdvancedConfig,
         max_retries: u32,
     ) -> (Self, tokio::sync::mpsc::UnboundedReceiver<UploadProgress>) {
         let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
@@ -315,15 +1864,48 @@ impl UploadManager {
         (
             Self {
                 s3_client,
-                parallel_limit,
+                scheduler: Scheduler::new(
+                    AdaptiveLimit::new(
+                        "File",
+                        advanced.parallel_uploads,
+                        advanced.max_parallel_uploads,
+                        advanced.adaptive_concurrency,
+                    ),
+                    advanced.upload_order,
+                ),
+                multipart_threshold_mb: advanced.multipart_threshold_mb,
+                multipart_chunk_mb: advanced.multipart_chunk_mb,
                 max_retries,
                 progress_tx: tx,
                 cancel_token: std::sync::Mutex::new(cancel_token),
+                jobs: JobRegistry::default(),
+                queue: None,
+                deferred_window: DeferredConfig::default(),
+                link_expiry: std::sync::Mutex::new(LinkExpiry::default()),
             },
             rx,
         )
     }
 
+    /// Keep queued files in `queue` until they upload, so they survive a crash or restart.
+    pub fn with_queue(mut self, queue: Arc<UploadQueue>) -> Self {
+        self.queue = Some(queue);
+        self
+    }
+
+    /// Start deferred uploads in `window` instead of the default 20:00-06:00.
+    pub fn with_deferred_window(mut self, window: DeferredConfig) -> Self {
+        self.deferred_window = window;
+        self
+    }
+
+    /// Presigned links of new uploads stay valid for `expiry` until changed.
+    pub fn with_link_expiry(self, expiry: LinkExpiry) -> Self {
+        self.set_link_expiry(expiry);
+        self
+    }
+
+    /// Cancel every upload in flight.
     pub fn cancel(&self) {
         if let Ok(token) = self.cancel_token.lock() {
             token.cancel();
@@ -340,59 +1922,795 @@ impl UploadManager {
         self.cancel_token.lock().map(|t| t.clone()).unwrap_or_else(|_| CancellationToken::new())
     }
 
-    pub async fn upload_files(&self, files: Vec<PathBuf>) -> Result<Vec<(String, String)>> {
-        use futures::stream::{self, StreamExt};
+    /// Stop starting new files and parts. Parts already being sent are finished.
+    pub fn pause(&self) {
+        if self.s3_client.pause_gate().pause() {
+            tracing::info!("Upload queue paused");
+        }
+    }
+
+    /// Continue paused uploads; multipart sessions pick up at the next unsent part.
+    pub fn resume(&self) {
+        if self.s3_client.pause_gate().resume() {
+            tracing::info!("Upload queue resumed");
+        }
+    }
 
-        let results = stream::iter(files)
-            .map(|file| self.upload_with_retry(file))
-            .buffer_unordered(self.parallel_limit)
-            .collect::<Vec<_>>()
+    pub fn is_paused(&self) -> bool {
+        self.s3_client.pause_gate().is_paused()
+    }
+
+    /// Uploads held because the endpoint cannot be reached.
+    pub fn waiting_for_network(&self) -> usize {
+        self.s3_client.network().waiting()
+    }
+
+    /// Hold an upload while the endpoint is unreachable, calling `on_wait` first.
+    /// Returns once it answers again or `cancel_token` fires.
+    async fn wait_for_network(&self, cancel_token: &CancellationToken, on_wait: impl FnOnce()) {
+        let network = self.s3_client.network();
+        if network.is_online() {
+            return;
+        }
+        on_wait();
+        tokio::select! {
+            () = cancel_token.cancelled() => {}
+            () = network.wait_online() => {}
+        }
+    }
+
+    /// Whether links expire, presigned or PARs.
+    pub fn expiring_links(&self) -> bool {
+        self.s3_client.expiring_links()
+    }
+
+    /// Whether links are OCI PARs, which can be listed and revoked.
+    pub fn par_links(&self) -> bool {
+        self.s3_client.par_links()
+    }
+
+    /// Validities that can be picked for new links; presigned ones are capped at 7 days.
+    pub fn link_expiries(&self) -> Vec<LinkExpiry> {
+        LinkExpiry::ALL
+            .into_iter()
+            .filter(|expiry| !self.s3_client.presigned_links() || expiry.presignable())
+            .collect()
+    }
+
+    pub fn link_expiry(&self) -> LinkExpiry {
+        self.link_expiry.lock().map(|e| *e).unwrap_or_default()
+    }
+
+    /// Validity of expiring links for uploads queued from now on.
+    pub fn set_link_expiry(&self, expiry: LinkExpiry) {
+        if let Ok(mut current) = self.link_expiry.lock() {
+            *current = expiry;
+        }
+    }
+
+    /// New expiring link to an object uploaded earlier, valid for `expiry`.
+    pub async fn renew_link(&self, key: &str, expiry: LinkExpiry) -> Result<ExpiringLink> {
+        self.s3_client.renew_link(key, expiry).await
+    }
+
+    /// Active PARs created by the app, soonest to expire first.
+    pub async fn list_pars(&self) -> Result<Vec<Par>> {
+        self.s3_client.list_pars().await
+    }
+
+    pub async fn revoke_par(&self, par_id: &str) -> Result<()> {
+        self.s3_client.revoke_par(par_id).await
+    }
+
+    /// Apply new rate limits to uploads already running and to new ones.
+    pub fn set_bandwidth(&self, config: &BandwidthConfig) {
+        self.s3_client.rate_limiter().set_config(config.clone());
+    }
+
+    /// Run `upload` until it finishes or `cancel_token` fires, calling `on_pause`
+    /// whenever the queue is paused or resumed meanwhile.
+    async fn drive_upload<T>(
+        &self,
+        upload: impl Future<Output = Result<T>>,
+        cancel_token: &CancellationToken,
+        on_pause: impl Fn(bool),
+    ) -> Result<T> {
+        let mut paused = self.s3_client.pause_gate().subscribe();
+        tokio::pin!(upload);
+        loop {
+            tokio::select! {
+                () = cancel_token.cancelled() => return Err(UploadError::Cancelled.into()),
+                result = &mut upload => return result,
+                Ok(()) = paused.changed() => on_pause(*paused.borrow_and_update()),
+            }
+        }
+    }
+
+    /// Cancel one queued or running upload. Returns false if `file_id` is not in progress.
+    pub fn cancel_file(&self, file_id: &str) -> bool {
+        self.jobs.cancel(file_id)
+    }
+
+    /// Run a failed or cancelled upload again under the same file id and object key.
+    pub async fn retry_file(&self, file_id: &str) -> Result<(String, UploadedObject)> {
+        let (source, token) = self.jobs.restart(&self.get_cancel_token(), file_id)?;
+        tracing::info!("Retrying upload {}", file_id);
+        self.schedule(file_id, &source, Priority::Normal);
+        if let Some(queue) = &self.queue {
+            // Back in the queue even if it was cancelled and dropped from it
+            queue.add_all(
+                queue_entry(file_id, &source, Utc::now(), None)
+                    .into_iter()
+                    .collect(),
+            );
+        }
+        self.run_job(file_id.to_string(), token, source).await
+    }
+
+    /// Drop a failed or cancelled upload that will not be retried.
+    pub fn forget_file(&self, file_id: &str) {
+        self.jobs.forget(file_id);
+        if let Some(queue) = &self.queue {
+            queue.remove(file_id);
+        }
+    }
+
+    /// Planned start for uploads deferred now: right away while the window is open,
+    /// otherwise when it next opens.
+    pub fn deferred_start(&self) -> DateTime<Utc> {
+        self.deferred_window
+            .next_start(chrono::Local::now().naive_local())
+            .and_local_timezone(chrono::Local)
+            .earliest()
+            .map_or_else(Utc::now, |start| start.with_timezone(&Utc))
+    }
+
+    /// Uploads waiting for their planned start, earliest first.
+    pub fn deferred_uploads(&self) -> Vec<DeferredUpload> {
+        self.jobs.deferred()
+    }
+
+    /// Start a deferred upload without waiting for the window.
+    /// Returns false if `file_id` is not waiting.
+    pub fn start_now(&self, file_id: &str) -> bool {
+        self.jobs.start_now(file_id)
+    }
+
+    pub fn start_all_deferred(&self) {
+        for upload in self.jobs.deferred() {
+            self.jobs.start_now(&upload.file_id);
+        }
+    }
+
+    /// Move a queued file ahead of everything else waiting for a slot.
+    /// Returns false if it is already uploading or finished.
+    pub fn move_to_front(&self, file_id: &str) -> bool {
+        self.scheduler.move_to_front(file_id)
+    }
+
+    /// Register `sources` as jobs under `batch`, line file jobs up for a slot with
+    /// `priority` and record them in the persistent queue. Jobs deferred to `start_at`
+    /// only get in line once they start.
+    fn enqueue(
+        &self,
+        batch: &CancellationToken,
+        sources: Vec<JobSource>,
+        priority: Priority,
+        start_at: Option<DateTime<Utc>>,
+    ) -> Vec<(String, CancellationToken, JobSource)> {
+        let jobs: Vec<_> = sources
+            .into_iter()
+            .map(|source| {
+                let (file_id, token) = self.jobs.register(batch, source.clone());
+                match start_at {
+                    Some(start_at) => self.jobs.defer(&file_id, start_at),
+                    None => self.schedule(&file_id, &source, priority),
+                }
+                (file_id, token, source)
+            })
+            .collect();
+
+        if let Some(queue) = &self.queue {
+            let queued_at = Utc::now();
+            queue.add_all(
+                jobs.iter()
+                    .filter_map(|(file_id, _, source)| {
+                        queue_entry(file_id, source, queued_at, start_at)
+                    })
+                    .collect(),
+            );
+        }
+        jobs
+    }
+
+    /// Token that the jobs of a new batch are registered under. Deferred jobs get
+    /// their own, so cancelling the uploads running now leaves them waiting.
+    fn batch_token(&self, start_at: Option<DateTime<Utc>>) -> CancellationToken {
+        match start_at {
+            Some(_) => CancellationToken::new(),
+            None => self.get_cancel_token(),
+        }
+    }
+
+    /// Offer files left in the persistent queue by a previous run for upload again.
+    /// They show up as failed uploads that can be retried. Files whose multipart
+    /// session is resumed automatically, or that are gone or changed, are dropped.
+    pub async fn restore_queue(&self) {
+        let Some(queue) = &self.queue else {
+            return;
+        };
+        let resumable = self.s3_client.resumable_sources().await;
+
+        for entry in queue.get_all() {
+            // Picked up by `resume_deferred`
+            if entry.state == QueueState::Pending && entry.start_at.is_some() {
+                continue;
+            }
+            if resumable.contains(&entry.source_path) {
+                queue.remove(&entry.file_id);
+                continue;
+            }
+            let snapshot = FileSnapshot {
+                size: entry.file_size,
+                modified: entry.modified,
+            };
+            if let Err(e) = snapshot.check(&entry.source_path) {
+                tracing::info!("Dropping queued upload of {}: {:#}", entry.filename, e);
+                queue.remove(&entry.file_id);
+                continue;
+            }
+
+            let reason = match entry.state {
+                QueueState::Pending => "Interrupted before the upload finished".to_string(),
+                QueueState::Failed => entry.error.unwrap_or_else(|| "Upload failed".to_string()),
+            };
+            tracing::info!("Restored queued upload of {}", entry.filename);
+            let _ = self.progress_tx.send(UploadProgress {
+                file_id: entry.file_id.clone(),
+                filename: entry.filename.clone(),
+                bytes_uploaded: 0,
+                total_bytes: entry.file_size,
+                status: UploadStatus::Failed(reason),
+            });
+            self.jobs.restore(
+                entry.file_id,
+                JobSource::File {
+                    path: entry.source_path,
+                    filename: entry.filename,
+                    s3_path: entry.s3_path,
+                    expected_source: Some(snapshot),
+                },
+            );
+        }
+    }
+
+    /// Put deferred uploads left in the persistent queue by a previous run back on hold.
+    /// A planned start that passed while the app was closed moves to the next window.
+    pub async fn resume_deferred(&self) -> BatchReport {
+        let Some(queue) = &self.queue else {
+            return BatchReport::default();
+        };
+
+        let batch = CancellationToken::new();
+        let mut jobs = Vec::new();
+        for entry in queue.get_all() {
+            let Some(start_at) = entry.start_at else {
+                continue;
+            };
+            if entry.state != QueueState::Pending {
+                continue;
+            }
+            let snapshot = FileSnapshot {
+                size: entry.file_size,
+                modified: entry.modified,
+            };
+            if let Err(e) = snapshot.check(&entry.source_path) {
+                tracing::info!("Dropping deferred upload of {}: {:#}", entry.filename, e);
+                queue.remove(&entry.file_id);
+                continue;
+            }
+
+            let start_at = if start_at > Utc::now() {
+                start_at
+            } else {
+                self.deferred_start()
+            };
+            let source = JobSource::File {
+                path: entry.source_path,
+                filename: entry.filename,
+                s3_path: entry.s3_path,
+                expected_source: Some(snapshot),
+            };
+            let token = self
+                .jobs
+                .register_as(&entry.file_id, &batch, source.clone());
+            self.jobs.defer(&entry.file_id, start_at);
+            jobs.push((entry.file_id, token, source));
+        }
+        if jobs.is_empty() {
+            return BatchReport::default();
+        }
+
+        tracing::info!("Restored {} deferred upload(s)", jobs.len());
+        self.run_batch(jobs).await
+    }
+
+    /// Hold a deferred job until its planned start, "start now" or cancellation,
+    /// then put it in line for a slot. Returns false if it was cancelled meanwhile.
+    async fn wait_for_start(
+        &self,
+        file_id: &str,
+        token: &CancellationToken,
+        source: &JobSource,
+    ) -> bool {
+        let Some((start_at, start_now)) = self.jobs.deferral(file_id) else {
+            return true;
+        };
+        tracing::info!("Upload of {} deferred until {}", source.name(), start_at);
+
+        loop {
+            let remaining = (start_at - Utc::now()).to_std().unwrap_or_default();
+            if remaining.is_zero() {
+                break;
+            }
+            tokio::select! {
+                () = token.cancelled() => {
+                    let _ = self.progress_tx.send(UploadProgress {
+                        file_id: file_id.to_string(),
+                        filename: source.name(),
+                        bytes_uploaded: 0,
+                        total_bytes: 0,
+                        status: UploadStatus::Cancelled,
+                    });
+                    return false;
+                }
+                () = start_now.notified() => break,
+                () = tokio::time::sleep(remaining.min(DEFERRED_CHECK_INTERVAL)) => {}
+            }
+        }
+
+        tracing::info!("Starting deferred upload of {}", source.name());
+        self.jobs.mark_started(file_id);
+        if let Some(queue) = &self.queue {
+            queue.mark_started(file_id);
+        }
+        self.schedule(file_id, source, Priority::Normal);
+        true
+    }
+
+    /// Put a file job in line for an upload slot. Archives are streamed as they are
+    /// built and do not wait for one.
+    fn schedule(&self, file_id: &str, source: &JobSource, priority: Priority) {
+        if let JobSource::File { path, .. } = source {
+            let size = std::fs::metadata(path).map_or(u64::MAX, |m| m.len());
+            self.scheduler.add(file_id, priority, size);
+        }
+    }
+
+    async fn run_job(
+        &self,
+        file_id: String,
+        token: CancellationToken,
+        source: JobSource,
+    ) -> Result<(String, UploadedObject)> {
+        // Read before the first await, so the job keeps the expiry chosen when it was queued
+        let link_expiry = self.link_expiry();
+        let result = if self.wait_for_start(&file_id, &token, &source).await {
+            match self.run_source(&file_id, &token, source).await {
+                Ok((filename, object)) => self
+                    .s3_client
+                    .share(object, link_expiry)
+                    .await
+                    .map(|object| (filename, object)),
+                Err(e) => Err(e),
+            }
+        } else {
+            Err(UploadError::Cancelled.into())
+        };
+        self.jobs.finish(&file_id, result.is_ok());
+        self.scheduler.remove(&file_id);
+
+        if let Some(queue) = &self.queue {
+            match result.as_ref().map_err(UploadError::classify) {
+                Err(UploadError::Cancelled) | Ok(_) => queue.remove(&file_id),
+                Err(error) => queue.mark_failed(&file_id, &error.to_string()),
+            }
+        }
+        result
+    }
+
+    async fn run_source(
+        &self,
+        file_id: &str,
+        token: &CancellationToken,
+        source: JobSource,
+    ) -> Result<(String, UploadedObject)> {
+        match source {
+            JobSource::File {
+                path,
+                filename,
+                s3_path,
+                expected_source,
+            } => match expected_source.map_or(Ok(()), |expected| expected.check(&path)) {
+                Ok(()) => {
+                    self.upload_with_retry(file_id, token, path, filename, s3_path)
+                        .await
+                }
+                Err(e) => {
+                    let _ = self.progress_tx.send(UploadProgress {
+                        file_id: file_id.to_string(),
+                        filename,
+                        bytes_uploaded: 0,
+                        total_bytes: 0,
+                        status: UploadStatus::Failed(format!("{e:#}")),
+                    });
+                    Err(e)
+                }
+            },
+            JobSource::Archive { paths } => self.upload_archive_job(file_id, token, paths).await,
+        }
+    }
+
+    /// Run `jobs` side by side and report how each of them ended.
+    async fn run_batch(&self, jobs: Vec<(String, CancellationToken, JobSource)>) -> BatchReport {
+        let files = futures::future::join_all(jobs.into_iter().map(
+            |(file_id, token, source)| async move {
+                let filename = source.name();
+                let outcome = match self.run_job(file_id.clone(), token, source).await {
+                    Ok((_, object)) => FileOutcome::Uploaded(object),
+                    Err(e) => match UploadError::classify(&e) {
+                        UploadError::Cancelled => FileOutcome::Cancelled,
+                        error => FileOutcome::Failed(error.to_string()),
+                    },
+                };
+                FileReport {
+                    file_id,
+                    filename,
+                    outcome,
+                }
+            },
+        ))
+        .await;
+        BatchReport { files }
+    }
+
+    /// Forget dedup index entries for objects deleted from the bucket.
+    pub async fn prune_dedup_index(&self) {
+        self.s3_client.prune_dedup_index().await;
+    }
+
+    /// Continue multipart uploads interrupted by a previous app exit.
+    pub async fn resume_interrupted(&self) -> BatchReport {
+        let sources = self.s3_client.resumable_sources().await;
+        if sources.is_empty() {
+            return BatchReport::default();
+        }
+
+        tracing::info!("Resuming {} interrupted upload(s)", sources.len());
+        self.upload_files(sources).await
+    }
+
+    /// Upload `files` in parallel and report the outcome of each one.
+    pub async fn upload_files(&self, files: Vec<PathBuf>) -> BatchReport {
+        self.upload_files_with_priority(files, Priority::Normal).await
+    }
+
+    /// Like `upload_files`, with `priority` deciding their place in the upload order.
+    pub async fn upload_files_with_priority(
+        &self,
+        files: Vec<PathBuf>,
+        priority: Priority,
+    ) -> BatchReport {
+        self.upload_file_list(files, priority, None).await
+    }
+
+    /// Like `upload_files`, with the files waiting in the queue until `start_at`.
+    /// The wait survives restarts through the persistent queue.
+    pub async fn upload_files_deferred(
+        &self,
+        files: Vec<PathBuf>,
+        start_at: DateTime<Utc>,
+    ) -> BatchReport {
+        self.upload_file_list(files, Priority::Normal, Some(start_at))
+            .await
+    }
+
+    async fn upload_file_list(
+        &self,
+        files: Vec<PathBuf>,
+        priority: Priority,
+        start_at: Option<DateTime<Utc>>,
+    ) -> BatchReport {
+        let batch = self.batch_token(start_at);
+        let sources = files
+            .into_iter()
+            .map(|file| {
+                let filename = file
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .unwrap_or("unknown")
+                    .to_string();
+                JobSource::File {
+                    s3_path: generate_s3_path(&filename),
+                    path: file,
+                    filename,
+                    expected_source: None,
+                }
+            })
+            .collect();
+
+        // All jobs start at once and the scheduler decides which of them upload
+        self.run_batch(self.enqueue(&batch, sources, priority, start_at))
+            .await
+    }
+
+    /// Upload every file of `root` under one random prefix that keeps relative paths,
+    /// then publish an index page as the folder-level link. With `start_at` the files
+    /// wait in the queue until then.
+    pub async fn upload_folder(
+        &self,
+        root: PathBuf,
+        start_at: Option<DateTime<Utc>>,
+    ) -> Result<FolderUpload> {
+        let link_expiry = self.link_expiry();
+        let folder_name = root
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("folder")
+            .to_string();
+        let files = folder::collect_files(&root)?;
+        if files.is_empty() {
+            anyhow::bail!("Folder {folder_name} has no files to upload");
+        }
+        tracing::info!("Uploading folder {} ({} files)", folder_name, files.len());
+
+        let prefix = generate_folder_prefix(&folder_name);
+        let keys = folder_object_keys(&prefix, files.iter().map(|f| f.relative.as_str()));
+
+        let batch = self.batch_token(start_at);
+        let sources = files
+            .into_iter()
+            .zip(keys)
+            .map(|(file, key)| JobSource::File {
+                path: file.path,
+                filename: file.relative,
+                s3_path: key,
+                expected_source: None,
+            })
+            .collect();
+
+        let results = futures::future::join_all(
+            self.enqueue(&batch, sources, Priority::Normal, start_at)
+                .into_iter()
+                .map(|(file_id, token, source)| self.run_job(file_id, token, source)),
+        )
+        .await;
+        let mut uploaded = without_cancelled_files(results, &batch)?;
+        if uploaded.is_empty() {
+            return Err(UploadError::Cancelled.into());
+        }
+        uploaded.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let listing: Vec<(String, String)> = uploaded
+            .iter()
+            .map(|(relative, object)| (relative.clone(), object.direct_url.clone()))
+            .collect();
+        let index_key = format!("{prefix}/index.html");
+        let url = self
+            .s3_client
+            .upload_folder_index(&index_key, &folder::index_html(&folder_name, &listing))
+            .await?;
+        let (url, expires_at, par_id) = if self.expiring_links() {
+            let link = self
+                .s3_client
+                .expiring_link(&index_key, link_expiry)
+                .await?;
+            (link.url, Some(link.expires_at), link.par_id)
+        } else {
+            (url, None, None)
+        };
+
+        Ok(FolderUpload {
+            name: folder_name,
+            url,
+            index_key,
+            expires_at,
+            par_id,
+            files: uploaded,
+        })
+    }
+
+    /// Pack `paths` into one ZIP streamed straight into a multipart upload.
+    /// The archive cannot be replayed, so a failed attempt is not retried automatically.
+    /// With `start_at` it waits until then, but unlike files not across restarts.
+    pub async fn upload_archive(
+        &self,
+        paths: Vec<PathBuf>,
+        start_at: Option<DateTime<Utc>>,
+    ) -> Result<(String, UploadedObject)> {
+        let source = JobSource::Archive { paths };
+        let (file_id, token) = self
+            .jobs
+            .register(&self.batch_token(start_at), source.clone());
+        if let Some(start_at) = start_at {
+            self.jobs.defer(&file_id, start_at);
+        }
+        self.run_job(file_id, token, source).await
+    }
+
+    async fn upload_archive_job(
+        &self,
+        file_id: &str,
+        cancel_token: &CancellationToken,
+        paths: Vec<PathBuf>,
+    ) -> Result<(String, UploadedObject)> {
+        let archive_name = archive::archive_name(&paths);
+        let entries = archive::collect_entries(&paths)?;
+        if entries.is_empty() {
+            anyhow::bail!("Nothing to archive");
+        }
+        tracing::info!("Archiving {} files into {}", entries.len(), archive_name);
+
+        let s3_path = generate_s3_path(&archive_name);
+        // Archive size is unknown upfront; the input size is close enough for progress
+        let total_bytes: u64 = entries.iter().map(|e| e.size).sum();
+        let send = |bytes_uploaded: u64, status: UploadStatus| {
+            let _ = self.progress_tx.send(UploadProgress {
+                file_id: file_id.to_string(),
+                filename: archive_name.clone(),
+                bytes_uploaded,
+                total_bytes,
+                status,
+            });
+        };
+
+        if cancel_token.is_cancelled() {
+            send(0, UploadStatus::Cancelled);
+            return Err(UploadError::Cancelled.into());
+        }
+        send(0, UploadStatus::Queued);
+        self.wait_for_network(cancel_token, || send(0, UploadStatus::WaitingForNetwork))
+            .await;
+        if cancel_token.is_cancelled() {
+            send(0, UploadStatus::Cancelled);
+            return Err(UploadError::Cancelled.into());
+        }
+        send(0, active_status(self.is_paused()));
+
+        let last_bytes = AtomicU64::new(0);
+        let result = self
+            .drive_upload(
+                self.s3_client.upload_stream_multipart(
+                    archive::zip_stream(entries),
+                    &s3_path,
+                    "application/zip",
+                    total_bytes,
+                    self.multipart_chunk_mb,
+                    |uploaded| {
+                        let uploaded = uploaded.min(total_bytes);
+                        last_bytes.store(uploaded, Ordering::Relaxed);
+                        send(uploaded, active_status(self.is_paused()));
+                    },
+                ),
+                cancel_token,
+                |paused| send(last_bytes.load(Ordering::Relaxed), active_status(paused)),
+            )
             .await;
 
-        results.into_iter().collect()
+        match result {
+            Ok(object) => {
+                send(total_bytes, UploadStatus::Completed);
+                Ok((archive_name, object))
+            }
+            Err(e) if cancel_token.is_cancelled() => {
+                send(0, UploadStatus::Cancelled);
+                Err(e)
+            }
+            Err(e) => {
+                send(0, UploadStatus::Failed(e.to_string()));
+                Err(e)
+            }
+        }
     }
 
-    async fn upload_with_retry(&self, file: PathBuf) -> Result<(String, String)> {
-        let original_filename = file
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("unknown")
-            .to_string();
+    /// Upload `file` to `s3_path`, retrying the whole file on failure.
+    /// `original_filename` is what gets reported back and recorded in history.
+    async fn upload_with_retry(
+        &self,
+        file_id: &str,
+        cancel_token: &CancellationToken,
+        file: PathBuf,
+        original_filename: String,
+        s3_path: String,
+    ) -> Result<(String, UploadedObject)> {
+        // Key is chosen once so every attempt targets the same object. Failed parts are
+        // already retried inside the multipart path; another attempt here resumes the
+        // journaled session, or starts a new one only if the session was lost.
         let mut attempts = 0;
         loop {
-            if self.get_cancel_token().is_cancelled() {
-                return Err(anyhow::anyhow!("Upload cancelled"));
-            }
-            
-            match self.upload_with_progress(file.clone()).await {
-                Ok(url) => return Ok((original_filename, url)),
-                Err(e) if e.to_string().contains("cancelled") => {
-                    return Err(e);
+            let e = match self
+                .upload_with_progress(file_id, cancel_token, file.clone(), &s3_path)
+                .await
+            {
+                Ok(object) => {
+                    // Reused objects were not sent and say nothing about throughput
+                    if !object.reused {
+                        self.scheduler.record(object.size, true);
+                    }
+                    return Ok((original_filename, object));
                 }
-                Err(e) if attempts < self.max_retries => {
+                Err(e) => e,
+            };
+            let error = UploadError::classify(&e);
+            // Time spent offline is not a failed attempt: the file waits until the
+            // endpoint answers again and the retry count starts over
+            if error.is_connectivity() && !self.s3_client.network().check().await {
+                tracing::warn!(
+                    "Upload of {} is waiting for the network: {}",
+                    original_filename,
+                    error
+                );
+                self.wait_for_network(cancel_token, || {
+                    let _ = self.progress_tx.send(UploadProgress {
+                        file_id: file_id.to_string(),
+                        filename: original_filename.clone(),
+                        bytes_uploaded: 0,
+                        total_bytes: std::fs::metadata(&file).map_or(0, |m| m.len()),
+                        status: UploadStatus::WaitingForNetwork,
+                    });
+                })
+                .await;
+                attempts = 0;
+                continue;
+            }
+            if error.is_retryable() {
+                self.scheduler.record(0, false);
+            }
+            match error {
+                UploadError::Cancelled => return Err(e),
+                _ if error.is_retryable() && attempts < self.max_retries => {
                     attempts += 1;
-                    let delay_secs = 2_u64.pow(attempts);
-                    tokio::time::sleep(Duration::from_secs(delay_secs)).await;
+                    let delay = error.retry_delay(attempts, UPLOAD_RETRY_BASE_DELAY);
+                    tracing::warn!(
+                        "Upload of {} failed (attempt {}), retrying in {:.1}s: {}",
+                        original_filename,
+                        attempts,
+                        delay.as_secs_f64(),
+                        error
+                    );
+                    tokio::select! {
+                        () = cancel_token.cancelled() => {}
+                        () = tokio::time::sleep(delay) => {}
+                    }
                 }
-                Err(e) => {
-                    let file_id = Uuid::new_v4().to_string();
-                    
+                _ => {
+                    tracing::error!(
+                        "Upload of {} failed permanently: {}",
+                        original_filename,
+                        error
+                    );
+
                     let _ = self.progress_tx.send(UploadProgress {
-                        file_id,
+                        file_id: file_id.to_string(),
                         filename: original_filename,
                         bytes_uploaded: 0,
                         total_bytes: 0,
-                        status: UploadStatus::Failed(e.to_string()),
+                        status: UploadStatus::Failed(error.to_string()),
                     });
-                    
+
                     return Err(e);
                 }
             }
         }
     }
 
-    async fn upload_with_progress(&self, file: PathBuf) -> Result<String> {
-        let file_id = Uuid::new_v4().to_string();
+    async fn upload_with_progress(
+        &self,
+        file_id: &str,
+        cancel_token: &CancellationToken,
+        file: PathBuf,
+        s3_path: &str,
+    ) -> Result<UploadedObject> {
         let filename = file
             .file_name()
             .and_then(|n| n.to_str())
@@ -404,98 +2722,265 @@ impl UploadManager {
             .with_context(|| format!("Failed to get file metadata: {}", file.display()))?
             .len();
 
-        let cancel_token = self.get_cancel_token();
-        
-        if cancel_token.is_cancelled() {
+        let send = |bytes_uploaded: u64, total_bytes: u64, status: UploadStatus| {
             self.progress_tx
                 .send(UploadProgress {
-                    file_id,
-                    filename,
-                    bytes_uploaded: 0,
+                    file_id: file_id.to_string(),
+                    filename: filename.clone(),
+                    bytes_uploaded,
                     total_bytes,
-                    status: UploadStatus::Cancelled,
+                    status,
                 })
-                .ok();
-            return Err(anyhow::anyhow!("Upload cancelled"));
+                .map_err(|_| anyhow::anyhow!("Progress channel closed"))
+        };
+
+        if cancel_token.is_cancelled() {
+            send(0, total_bytes, UploadStatus::Cancelled).ok();
+            return Err(UploadError::Cancelled.into());
         }
 
-        self.progress_tx
-            .send(UploadProgress {
-                file_id: file_id.clone(),
-                filename: filename.clone(),
-                bytes_uploaded: 0,
-                total_bytes,
-                status: UploadStatus::Queued,
-            })
-            .map_err(|_| anyhow::anyhow!("Progress channel closed"))?;
+        send(0, total_bytes, UploadStatus::Queued)?;
+
+        // A paused queue holds files back before their first request
+        if self.is_paused() {
+            send(0, total_bytes, UploadStatus::Paused)?;
+            tokio::select! {
+                () = cancel_token.cancelled() => {}
+                () = self.s3_client.pause_gate().wait_resumed() => {}
+            }
+        }
 
-        // Clone once for callback and Uploading status
-        let file_id_for_callback = file_id.clone();
-        let filename_for_callback = filename.clone();
+        // Known to be offline: wait for the endpoint before taking a slot
+        self.wait_for_network(cancel_token, || {
+            let _ = send(0, total_bytes, UploadStatus::WaitingForNetwork);
+        })
+        .await;
 
-        self.progress_tx
-            .send(UploadProgress {
-                file_id: file_id_for_callback.clone(),
-                filename: filename_for_callback.clone(),
-                bytes_uploaded: 0,
-                total_bytes,
-                status: UploadStatus::Uploading,
-            })
-            .map_err(|_| anyhow::anyhow!("Progress channel closed"))?;
+        // Files wait for a slot in the scheduler's order, within the concurrency limit
+        let ticket = tokio::select! {
+            () = cancel_token.cancelled() => None,
+            ticket = self.scheduler.acquire(file_id) => Some(ticket),
+        };
+        let Some(_ticket) = ticket else {
+            send(0, total_bytes, UploadStatus::Cancelled).ok();
+            return Err(UploadError::Cancelled.into());
+        };
 
-        let progress_tx = self.progress_tx.clone();
-        
-        let url = tokio::select! {
-            () = cancel_token.cancelled() => {
-                self.progress_tx
-                    .send(UploadProgress {
-                        file_id,
-                        filename,
-                        bytes_uploaded: 0,
+        send(0, total_bytes, UploadStatus::Uploading)?;
+
+        let last_bytes = AtomicU64::new(0);
+        let result = self
+            .drive_upload(
+                self.s3_client.upload_file_auto_with_progress(
+                    &file,
+                    s3_path,
+                    self.multipart_threshold_mb,
+                    self.multipart_chunk_mb,
+                    |uploaded, total| {
+                        last_bytes.store(uploaded, Ordering::Relaxed);
+                        let _ = send(uploaded, total, active_status(self.is_paused()));
+                    },
+                ),
+                cancel_token,
+                |paused| {
+                    let _ = send(
+                        last_bytes.load(Ordering::Relaxed),
                         total_bytes,
-                        status: UploadStatus::Cancelled,
-                    })
-                    .ok();
-                return Err(anyhow::anyhow!("Upload cancelled"));
-            }
-            result = self.s3_client.upload_file_auto_with_progress(&file, 5, 5, |uploaded, total| {
-                let _ = progress_tx.send(UploadProgress {
-                    file_id: file_id_for_callback.clone(),
-                    filename: filename_for_callback.clone(),
-                    bytes_uploaded: uploaded,
-                    total_bytes: total,
-                    status: UploadStatus::Uploading,
-                });
-            }) => {
-                result?
+                        active_status(paused),
+                    );
+                },
+            )
+            .await;
+
+        let object = match result {
+            Ok(object) => object,
+            Err(_) if cancel_token.is_cancelled() => {
+                self.s3_client.discard_resumable(&file).await;
+                send(0, total_bytes, UploadStatus::Cancelled).ok();
+                return Err(UploadError::Cancelled.into());
             }
+            Err(e) => return Err(e),
         };
 
-        self.progress_tx
-            .send(UploadProgress {
-                file_id,
-                filename,
-                bytes_uploaded: total_bytes,
-                total_bytes,
-                status: UploadStatus::Completed,
-            })
-            .map_err(|_| anyhow::anyhow!("Progress channel closed"))?;
+        send(total_bytes, total_bytes, UploadStatus::Completed)?;
 
-        Ok(url)
+        Ok(object)
+    }
+
+}
+
+/// Persistent queue record of a file job. Archives are built from several sources
+/// and are not kept.
+fn queue_entry(
+    file_id: &str,
+    source: &JobSource,
+    queued_at: DateTime<Utc>,
+    start_at: Option<DateTime<Utc>>,
+) -> Option<QueueEntry> {
+    let JobSource::File {
+        path,
+        filename,
+        s3_path,
+        ..
+    } = source
+    else {
+        return None;
+    };
+    let snapshot = FileSnapshot::of(path).ok()?;
+    Some(QueueEntry {
+        file_id: file_id.to_string(),
+        source_path: path.clone(),
+        filename: filename.clone(),
+        s3_path: s3_path.clone(),
+        file_size: snapshot.size,
+        modified: snapshot.modified,
+        queued_at,
+        state: QueueState::Pending,
+        error: None,
+        start_at,
+    })
+}
+
+/// Status of a started upload while the queue is paused or running.
+fn active_status(paused: bool) -> UploadStatus {
+    if paused {
+        UploadStatus::Paused
+    } else {
+        UploadStatus::Uploading
+    }
+}
+
+/// Client for requests that carry file data. reqwest's read timeout runs from the
+/// start of a request until the response headers, which would cut off long uploads;
+/// stalls are caught by the body watchdog instead.
+fn http_client(config: &Config) -> Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(
+            config.advanced.connect_timeout_secs.max(1).into(),
+        ))
+        .tcp_keepalive(TCP_KEEPALIVE)
+        .build()
+        .context("Failed to create HTTP client")
+}
+
+/// Results of a batch without the files that were cancelled on their own.
+/// Once the whole batch is cancelled, the cancellation is reported as its result.
+fn without_cancelled_files<T>(
+    results: Vec<Result<T>>,
+    batch: &CancellationToken,
+) -> Result<Vec<T>> {
+    results
+        .into_iter()
+        .filter(|result| match result {
+            Err(e) => batch.is_cancelled() || UploadError::classify(e) != UploadError::Cancelled,
+            Ok(_) => true,
+        })
+        .collect()
+}
+
+/// SHA-256 and MD5 of `path`, read in a single streaming pass.
+async fn hash_file(path: &Path) -> Result<FileDigest> {
+    let mut file = tokio::fs::File::open(path)
+        .await
+        .with_context(|| format!("Failed to open file: {}", path.display()))?;
+    let mut digest = StreamDigest::default();
+    let mut buf = vec![0u8; STREAM_BUFFER_SIZE];
+    loop {
+        let read = file
+            .read(&mut buf)
+            .await
+            .with_context(|| format!("Failed to read file: {}", path.display()))?;
+        if read == 0 {
+            break;
+        }
+        digest.update(&buf[..read]);
+    }
+    Ok(digest.finish())
+}
+
+/// Base64 MD5 of `data`, as sent in `Content-MD5`.
+fn base64_md5(data: &[u8]) -> String {
+    use base64::Engine;
+
+    base64::engine::general_purpose::STANDARD.encode(Md5::digest(data))
+}
+
+/// Part size for `file_size` based on the configured chunk size.
+/// Grows the chunk (in whole MiB) when the file would otherwise need more than `MAX_PARTS` parts.
+fn effective_chunk_size(file_size: u64, chunk_size_mb: u32) -> Result<u64> {
+    let configured = (u64::from(chunk_size_mb) * MIB).max(MIN_PART_SIZE);
+    if file_size.div_ceil(configured) <= MAX_PARTS {
+        return Ok(configured);
     }
 
+    let required = file_size.div_ceil(MAX_PARTS).div_ceil(MIB) * MIB;
+    if required > MAX_PART_SIZE {
+        anyhow::bail!("File too large for multipart upload: {file_size} bytes");
+    }
+    Ok(required)
+}
+
+/// Request body streamed from `file` that publishes the running byte count to `sent`
+/// and feeds what it reads into `digest`.
+fn progress_body(
+    file: tokio::fs::File,
+    sent: tokio::sync::watch::Sender<u64>,
+    digest: Arc<std::sync::Mutex<StreamDigest>>,
+    limiter: Arc<RateLimiter>,
+    timer: Arc<StallTimer>,
+) -> ByteStream {
+    let hashed = counting_stream(file, sent).inspect_ok(move |chunk| {
+        if let Ok(mut digest) = digest.lock() {
+            digest.update(chunk);
+        }
+    });
+    throttled(hashed, limiter, timer).boxed()
+}
+
+/// Hold back each chunk of `stream` until `limiter` lets it through, and touch
+/// `timer` as chunks are handed on. The limiter wait does not count as a stall.
+fn throttled<S>(
+    stream: S,
+    limiter: Arc<RateLimiter>,
+    timer: Arc<StallTimer>,
+) -> impl futures::Stream<Item = std::io::Result<Bytes>>
+where
+    S: futures::Stream<Item = std::io::Result<Bytes>>,
+{
+    stream.and_then(move |chunk| {
+        let limiter = limiter.clone();
+        let timer = timer.clone();
+        async move {
+            {
+                let _hold = timer.hold();
+                limiter.acquire(chunk.len() as u64).await;
+            }
+            timer.touch();
+            Ok(chunk)
+        }
+    })
+}
+
+fn counting_stream<R: AsyncRead>(
+    reader: R,
+    sent: tokio::sync::watch::Sender<u64>,
+) -> impl futures::Stream<Item = std::io::Result<Bytes>> {
+    let mut total: u64 = 0;
+    ReaderStream::with_capacity(reader, STREAM_BUFFER_SIZE).inspect_ok(move |chunk| {
+        total += chunk.len() as u64;
+        sent.send_replace(total);
+    })
+}
+
+/// Size of `part_number` (1-based) when `file_size` is split into `chunk_size` parts.
+fn part_size(file_size: u64, chunk_size: u64, part_number: u32) -> u64 {
+    let offset = u64::from(part_number - 1) * chunk_size;
+    std::cmp::min(chunk_size, file_size.saturating_sub(offset))
 }
 
-fn build_public_url(
-    namespace: &str,
-    region: &str,
-    bucket: &str,
-    key: &str,
-) -> String {
-    // https://NAMESPACE.objectstorage.REGION.oci.customer-oci.com/n/NAMESPACE/b/BUCKET/o/KEY
-    format!(
-        "https://{namespace}.objectstorage.{region}.oci.customer-oci.com/n/{namespace}/b/{bucket}/o/{key}"
-    )
+/// The server no longer knows the upload id (aborted or expired session).
+fn is_session_lost(error: &anyhow::Error) -> bool {
+    UploadError::classify(error) == UploadError::SessionLost
 }
 
 fn sanitize_filename(name: &str) -> String {
@@ -543,9 +3028,54 @@ fn generate_s3_path(filename: &str) -> String {
     format!("{date}/{name}_{uuid}{ext}")
 }
 
+/// Shared prefix for a folder upload: `DATE/FOLDER_UUID`.
+fn generate_folder_prefix(folder_name: &str) -> String {
+    let date = chrono::Utc::now().format("%Y-%m-%d").to_string();
+    let uuid = generate_uuid16();
+    let sanitized = sanitize_filename(folder_name);
+    let name = if sanitized.is_empty() { "folder" } else { sanitized.as_str() };
+
+    format!("{date}/{name}_{uuid}")
+}
+
+/// Object keys under `prefix` for `/`-separated relative paths. Each component is
+/// sanitized; names that collide after sanitizing get a numeric suffix.
+fn folder_object_keys<'a>(
+    prefix: &str,
+    relative_paths: impl IntoIterator<Item = &'a str>,
+) -> Vec<String> {
+    let mut used = HashSet::new();
+    relative_paths
+        .into_iter()
+        .map(|relative| {
+            let sanitized = relative
+                .split('/')
+                .map(|component| match sanitize_filename(component) {
+                    name if name.is_empty() || name.starts_with('.') => format!("file{name}"),
+                    name => name,
+                })
+                .collect::<Vec<_>>()
+                .join("/");
+
+            let mut key = format!("{prefix}/{sanitized}");
+            let (stem, ext) = match sanitized.rfind('.') {
+                Some(pos) if !sanitized[pos..].contains('/') => sanitized.split_at(pos),
+                _ => (sanitized.as_str(), ""),
+            };
+            let mut n = 2;
+            while !used.insert(key.clone()) {
+                key = format!("{prefix}/{stem}-{n}{ext}");
+                n += 1;
+            }
+            key
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use s3::error::S3Error;
 
     #[test]
     fn test_sanitize_filename() {
@@ -633,6 +3163,209 @@ mod tests {
         assert_eq!(chunks[2].len(), 2 * 1024 * 1024);
     }
 
+    #[tokio::test]
+    async fn test_chunk_pool_reuses_buffers() {
+        let pool = ChunkPool::new(2, 1024);
+        let mut reader: &[u8] = &[7u8; 1024];
+
+        let capacity = {
+            let mut chunk = pool.acquire().await;
+            chunk.fill_from(&mut reader, 1024).await.unwrap();
+            assert_eq!(chunk.as_slice().len(), 1024);
+            chunk.buf.capacity()
+        };
+
+        let chunk = pool.acquire().await;
+        assert!(chunk.as_slice().is_empty());
+        assert_eq!(chunk.buf.capacity(), capacity);
+    }
+
+    #[tokio::test]
+    async fn test_chunk_body_shares_pooled_buffer() {
+        let pool = ChunkPool::new(1, 1024);
+        let mut reader: &[u8] = &[7u8; 2048];
+        let mut chunk = pool.acquire().await;
+        chunk.fill_from(&mut reader, 1024).await.unwrap();
+        let ptr = chunk.as_slice().as_ptr();
+
+        let body = chunk.take_bytes();
+        assert_eq!(body.as_ptr(), ptr);
+        chunk.restore(body);
+        assert_eq!(chunk.as_slice().as_ptr(), ptr);
+        assert_eq!(chunk.as_slice().len(), 1024);
+
+        // A body still referenced elsewhere is not taken back
+        let body = chunk.take_bytes();
+        let _shared = body.clone();
+        chunk.restore(body);
+        assert!(chunk.as_slice().is_empty());
+        chunk.fill_from(&mut reader, 1024).await.unwrap();
+        assert_eq!(chunk.as_slice(), &[7u8; 1024]);
+    }
+
+    #[tokio::test]
+    async fn test_chunk_pool_is_bounded() {
+        let pool = ChunkPool::new(2, 16);
+        let first = pool.acquire().await;
+        let _second = pool.acquire().await;
+
+        let blocked = tokio::time::timeout(Duration::from_millis(50), pool.acquire()).await;
+        assert!(blocked.is_err());
+
+        drop(first);
+        let third = tokio::time::timeout(Duration::from_millis(50), pool.acquire()).await;
+        assert!(third.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_buffer_budget_is_shared_between_pools() {
+        let budget = BufferBudget::new(2);
+        let first = ChunkPool::new(2, MIB as usize).with_budget(&budget);
+        let second = ChunkPool::new(2, MIB as usize).with_budget(&budget);
+
+        let _a = first.acquire().await;
+        let b = second.acquire().await;
+        let blocked = tokio::time::timeout(Duration::from_millis(50), first.acquire()).await;
+        assert!(blocked.is_err());
+        assert!(first.try_acquire().is_none());
+        assert!(first.acquire_next(true).await.is_none());
+
+        // A returned buffer keeps its share until its pool lets it go
+        drop(b);
+        assert!(first.try_acquire().is_none());
+        second.release_idle();
+        let freed = tokio::time::timeout(Duration::from_millis(50), first.acquire()).await;
+        assert!(freed.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_buffer_budget_admits_oversized_chunk() {
+        let budget = BufferBudget::new(1);
+        let pool = ChunkPool::new(1, 8 * MIB as usize).with_budget(&budget);
+
+        let chunk = tokio::time::timeout(Duration::from_millis(50), pool.acquire()).await;
+        assert!(chunk.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_hash_file() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("hello.txt");
+        std::fs::write(&path, b"hello world").unwrap();
+
+        let digest = hash_file(&path).await.unwrap();
+        assert_eq!(
+            digest.sha256,
+            "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9"
+        );
+        assert_eq!(digest.md5, "XrY7u+Ae7tCTyyK7j1rNww==");
+    }
+
+    #[test]
+    fn test_uploaded_object_history_entry() {
+        let object = UploadedObject {
+            key: "2026-02-06/a_0123456789abcdef.txt".to_string(),
+            url: "https://example.com/a.txt".to_string(),
+            direct_url: "https://bucket.example.com/a.txt".to_string(),
+            expires_at: None,
+            par_id: None,
+            size: 11,
+            sha256: "ab".repeat(32),
+            reused: true,
+        };
+
+        let entry = object.history_entry("a.txt");
+        assert_eq!(entry.filename, "a.txt");
+        assert_eq!(entry.url, object.url);
+        assert_eq!(entry.direct_url, Some(object.direct_url.clone()));
+        assert_eq!(entry.key, Some(object.key.clone()));
+        assert_eq!(entry.size, 11);
+        assert_eq!(entry.sha256, Some(object.sha256));
+        assert!(entry.dedup_hit);
+    }
+
+    #[tokio::test]
+    async fn test_chunk_fill_up_to_stops_at_end_of_stream() {
+        let pool = ChunkPool::new(1, 8);
+        let mut reader: &[u8] = &[1u8; 12];
+
+        let mut chunk = pool.acquire().await;
+        assert_eq!(chunk.fill_up_to(&mut reader, 8).await.unwrap(), 8);
+        assert_eq!(chunk.fill_up_to(&mut reader, 8).await.unwrap(), 4);
+        assert_eq!(chunk.as_slice(), &[1u8; 4]);
+        assert_eq!(chunk.fill_up_to(&mut reader, 8).await.unwrap(), 0);
+        assert!(chunk.as_slice().is_empty());
+    }
+
+    #[test]
+    fn test_part_size() {
+        let chunk = 5 * 1024 * 1024;
+        let file_size = 12 * 1024 * 1024;
+
+        assert_eq!(part_size(file_size, chunk, 1), chunk);
+        assert_eq!(part_size(file_size, chunk, 2), chunk);
+        assert_eq!(part_size(file_size, chunk, 3), 2 * 1024 * 1024);
+        assert_eq!(part_size(2 * chunk, chunk, 2), chunk);
+    }
+
+    #[test]
+    fn test_session_lost_detection() {
+        let lost = anyhow::Error::new(S3Error::HttpFailWithBody(
+            404,
+            "<Error><Code>NoSuchUpload</Code></Error>".to_string(),
+        ));
+        let other = anyhow::Error::new(S3Error::HttpFailWithBody(
+            500,
+            "InternalError".to_string(),
+        ));
+
+        assert!(is_session_lost(&lost));
+        assert!(!is_session_lost(&other));
+    }
+
+    #[test]
+    fn test_effective_chunk_size_uses_configured_value() {
+        assert_eq!(effective_chunk_size(100 * MIB, 8).unwrap(), 8 * MIB);
+        assert_eq!(effective_chunk_size(10_000 * 5 * MIB, 5).unwrap(), 5 * MIB);
+    }
+
+    #[test]
+    fn test_effective_chunk_size_enforces_minimum() {
+        assert_eq!(effective_chunk_size(100 * MIB, 1).unwrap(), MIN_PART_SIZE);
+        assert_eq!(effective_chunk_size(100 * MIB, 0).unwrap(), MIN_PART_SIZE);
+    }
+
+    #[test]
+    fn test_effective_chunk_size_grows_for_large_files() {
+        let file_size = 50 * 1024 * MIB;
+        let chunk = effective_chunk_size(file_size, 5).unwrap();
+
+        assert!(chunk > 5 * MIB);
+        assert_eq!(chunk % MIB, 0);
+        assert!(file_size.div_ceil(chunk) <= MAX_PARTS);
+    }
+
+    #[test]
+    fn test_effective_chunk_size_rejects_oversized_files() {
+        let file_size = MAX_PARTS * MAX_PART_SIZE + 1;
+        assert!(effective_chunk_size(file_size, 5).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_counting_stream_reports_sent_bytes() {
+        let data = vec![1u8; STREAM_BUFFER_SIZE * 2 + 100];
+        let (tx, rx) = tokio::sync::watch::channel(0u64);
+
+        let chunks: Vec<_> = counting_stream(data.as_slice(), tx)
+            .try_collect()
+            .await
+            .unwrap();
+
+        let streamed: usize = chunks.iter().map(|c: &Bytes| c.len()).sum();
+        assert_eq!(streamed, data.len());
+        assert_eq!(*rx.borrow(), data.len() as u64);
+    }
+
     #[test]
     fn test_multipart_part_numbering() {
         let parts = vec![vec![1u8; 100], vec![2u8; 100], vec![3u8; 100]];
@@ -751,6 +3484,179 @@ mod tests {
         assert!(token2.is_cancelled());
     }
 
+    fn sample_job() -> JobSource {
+        JobSource::File {
+            path: PathBuf::from("a.txt"),
+            filename: "a.txt".to_string(),
+            s3_path: "2026-02-06/a_0123456789abcdef.txt".to_string(),
+            expected_source: None,
+        }
+    }
+
+    #[test]
+    fn test_job_cancel_is_per_file() {
+        let jobs = JobRegistry::default();
+        let batch = CancellationToken::new();
+        let (first_id, first) = jobs.register(&batch, sample_job());
+        let (_, second) = jobs.register(&batch, sample_job());
+
+        assert!(jobs.cancel(&first_id));
+        assert!(first.is_cancelled());
+        assert!(!second.is_cancelled());
+
+        batch.cancel();
+        assert!(second.is_cancelled());
+        assert!(!jobs.cancel("unknown"));
+    }
+
+    #[test]
+    fn test_job_restart_after_failure() {
+        let jobs = JobRegistry::default();
+        let batch = CancellationToken::new();
+        let (file_id, token) = jobs.register(&batch, sample_job());
+
+        assert!(jobs.restart(&batch, &file_id).is_err());
+
+        token.cancel();
+        jobs.finish(&file_id, false);
+        assert!(!jobs.cancel(&file_id));
+
+        let (source, token) = jobs.restart(&batch, &file_id).unwrap();
+        assert!(!token.is_cancelled());
+        assert!(matches!(source, JobSource::File { filename, .. } if filename == "a.txt"));
+
+        jobs.finish(&file_id, true);
+        assert!(jobs.restart(&batch, &file_id).is_err());
+    }
+
+    #[test]
+    fn test_job_forget_keeps_running_jobs() {
+        let jobs = JobRegistry::default();
+        let batch = CancellationToken::new();
+        let (running, _) = jobs.register(&batch, sample_job());
+        let (failed, _) = jobs.register(&batch, sample_job());
+        jobs.finish(&failed, false);
+
+        jobs.forget(&running);
+        jobs.forget(&failed);
+
+        assert!(jobs.cancel(&running));
+        assert!(jobs.restart(&batch, &failed).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_deferred_job_starts_now() {
+        let jobs = JobRegistry::default();
+        let batch = CancellationToken::new();
+        let start_at = Utc::now() + chrono::Duration::hours(8);
+        let (waiting, _) = jobs.register(&batch, sample_job());
+        let (running, _) = jobs.register(&batch, sample_job());
+        jobs.defer(&waiting, start_at);
+
+        let deferred = jobs.deferred();
+        assert_eq!(deferred.len(), 1);
+        assert_eq!(deferred[0].file_id, waiting);
+        assert_eq!(deferred[0].start_at, start_at);
+        assert!(!jobs.start_now(&running));
+
+        let (_, start_now) = jobs.deferral(&waiting).unwrap();
+        assert!(jobs.start_now(&waiting));
+        tokio::time::timeout(Duration::from_millis(50), start_now.notified())
+            .await
+            .unwrap();
+
+        jobs.mark_started(&waiting);
+        assert!(jobs.deferred().is_empty());
+        assert!(!jobs.start_now(&waiting));
+    }
+
+    #[test]
+    fn test_file_snapshot_detects_changes() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("a.txt");
+        std::fs::write(&path, b"hello").unwrap();
+
+        let snapshot = FileSnapshot::of(&path).unwrap();
+        assert!(snapshot.check(&path).is_ok());
+
+        std::fs::write(&path, b"hello world").unwrap();
+        assert!(snapshot.check(&path).is_err());
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(snapshot.check(&path).is_err());
+    }
+
+    #[test]
+    fn test_queue_entry_skips_archives() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("a.txt");
+        std::fs::write(&path, b"hello").unwrap();
+        let file = JobSource::File {
+            path: path.clone(),
+            filename: "a.txt".to_string(),
+            s3_path: "2026-02-06/a_0123456789abcdef.txt".to_string(),
+            expected_source: None,
+        };
+        let archive = JobSource::Archive { paths: vec![path] };
+
+        let entry = queue_entry("id-1", &file, Utc::now(), None).unwrap();
+        assert_eq!(entry.file_size, 5);
+        assert_eq!(entry.state, QueueState::Pending);
+        assert!(queue_entry("id-2", &archive, Utc::now(), None).is_none());
+    }
+
+    #[test]
+    fn test_batch_report_keeps_successes_next_to_failures() {
+        let object = UploadedObject {
+            key: "2026-02-06/a_0123456789abcdef.txt".to_string(),
+            url: "https://example.com/a.txt".to_string(),
+            direct_url: "https://example.com/a.txt".to_string(),
+            expires_at: None,
+            par_id: None,
+            size: 5,
+            sha256: "abc".to_string(),
+            reused: false,
+        };
+        let report = |filename: &str, outcome| FileReport {
+            file_id: format!("id-{filename}"),
+            filename: filename.to_string(),
+            outcome,
+        };
+        let batch = BatchReport {
+            files: vec![
+                report("a.txt", FileOutcome::Uploaded(object)),
+                report("b.txt", FileOutcome::Failed("Access denied".to_string())),
+                report("c.txt", FileOutcome::Cancelled),
+            ],
+        };
+
+        let uploaded: Vec<_> = batch.uploaded().collect();
+        assert_eq!(uploaded.len(), 1);
+        assert_eq!(uploaded[0].0, "a.txt");
+        assert_eq!(uploaded[0].1.url, "https://example.com/a.txt");
+        assert_eq!(
+            batch.failed().collect::<Vec<_>>(),
+            vec![("b.txt", "Access denied")]
+        );
+        assert_eq!(batch.cancelled_count(), 1);
+    }
+
+    #[test]
+    fn test_without_cancelled_files() {
+        let batch = CancellationToken::new();
+        let results = || -> Vec<Result<u32>> {
+            vec![Ok(1), Err(UploadError::Cancelled.into()), Ok(3)]
+        };
+
+        assert_eq!(without_cancelled_files(results(), &batch).unwrap(), vec![1, 3]);
+
+        let failed: Vec<Result<u32>> = vec![Ok(1), Err(anyhow::anyhow!("Upload failed"))];
+        assert!(without_cancelled_files(failed, &batch).is_err());
+
+        batch.cancel();
+        assert!(without_cancelled_files(results(), &batch).is_err());
+    }
+
     #[test]
     fn test_folder_recursive_enumeration() {
         use std::fs;
@@ -871,16 +3777,294 @@ mod tests {
     }
 
     #[test]
-    fn test_build_public_url() {
-        let url = build_public_url(
-            "myns",
-            "eu-frankfurt-1",
-            "my-bucket",
-            "2026-02-06/test_abc123.png",
+    fn test_generate_folder_prefix() {
+        let prefix = generate_folder_prefix("Moje Dokumenty");
+
+        let parts: Vec<&str> = prefix.split('/').collect();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].len(), 10);
+        assert!(parts[1].starts_with("moje-dokumenty_"));
+        assert_eq!(parts[1].len(), "moje-dokumenty_".len() + 16);
+    }
+
+    #[test]
+    fn test_folder_object_keys() {
+        let keys = folder_object_keys(
+            "2026-02-06/docs_0123456789abcdef",
+            ["Raport Q1.pdf", "zdjęcia/Plaża.JPG", "zdjecia/plaza.jpg", "日本.txt"],
         );
+
         assert_eq!(
-            url,
-            "https://myns.objectstorage.eu-frankfurt-1.oci.customer-oci.com/n/myns/b/my-bucket/o/2026-02-06/test_abc123.png"
+            keys,
+            vec![
+                "2026-02-06/docs_0123456789abcdef/raport-q1.pdf",
+                "2026-02-06/docs_0123456789abcdef/zdjecia/plaza.jpg",
+                "2026-02-06/docs_0123456789abcdef/zdjecia/plaza-2.jpg",
+                "2026-02-06/docs_0123456789abcdef/file.txt",
+            ]
+        );
+    }
+
+    /// Config writing to the local backend under `root`.
+    fn local_config(root: &Path) -> Config {
+        toml::from_str(&format!(
+            r#"
+[storage]
+backend = "local"
+local_path = '{}'
+
+[app]
+auto_copy_link = false
+auto_start = false
+
+[advanced]
+parallel_uploads = 2
+multipart_threshold_mb = 5
+multipart_chunk_mb = 5
+"#,
+            root.display()
+        ))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_upload_files_to_local_store() {
+        let storage = tempfile::TempDir::new().unwrap();
+        let sources = tempfile::TempDir::new().unwrap();
+        let small = sources.path().join("notes.txt");
+        std::fs::write(&small, b"hello").unwrap();
+        // Over the multipart threshold, sent as three parts
+        let large = sources.path().join("video.bin");
+        let content: Vec<u8> = (0..11 * MIB).map(|i| (i % 251) as u8).collect();
+        std::fs::write(&large, &content).unwrap();
+
+        let config = local_config(storage.path());
+        let client = S3Client::new(&config).await.unwrap();
+        let (manager, _progress_rx) = UploadManager::new(client, &config.advanced, 0);
+        let report = manager.upload_files(vec![small, large]).await;
+
+        assert_eq!(report.failed().count(), 0);
+        assert_eq!(report.uploaded().count(), 2);
+        for (filename, object) in report.uploaded() {
+            let source = std::fs::read(sources.path().join(filename)).unwrap();
+            assert_eq!(
+                std::fs::read(storage.path().join(&object.key)).unwrap(),
+                source
+            );
+            assert!(object.url.starts_with("file://"));
+            assert_eq!(object.sha256, format!("{:x}", Sha256::digest(&source)));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dedup_reuses_and_prunes_deleted_objects() {
+        let storage = tempfile::TempDir::new().unwrap();
+        let sources = tempfile::TempDir::new().unwrap();
+        let path = sources.path().join("notes.txt");
+        std::fs::write(&path, b"hello").unwrap();
+
+        let index = Arc::new(DedupIndex::new(sources.path().join("dedup_index.json")).unwrap());
+        let client = S3Client::new(&local_config(storage.path()))
+            .await
+            .unwrap()
+            .with_dedup_index(index.clone(), true);
+
+        let first = client
+            .upload_file_auto_with_progress(&path, "a/notes.txt", 5, 5, |_, _| {})
+            .await
+            .unwrap();
+        let second = client
+            .upload_file_auto_with_progress(&path, "b/notes.txt", 5, 5, |_, _| {})
+            .await
+            .unwrap();
+        assert!(second.reused);
+        assert_eq!(second.key, first.key);
+
+        client.prune_dedup_index().await;
+        assert_eq!(index.entries_for(&client.store.name()).len(), 1);
+
+        client.store.delete(&first.key).await.unwrap();
+        client.prune_dedup_index().await;
+        assert!(index.entries_for(&client.store.name()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_resumed_upload_hashes_whole_file() {
+        use crate::upload_journal::JournalPart;
+
+        let storage = tempfile::TempDir::new().unwrap();
+        let sources = tempfile::TempDir::new().unwrap();
+        let content: Vec<u8> = (0..11 * MIB).map(|i| (i % 251) as u8).collect();
+        let path = sources.path().join("video.bin");
+        std::fs::write(&path, &content).unwrap();
+        let metadata = std::fs::metadata(&path).unwrap();
+
+        let journal = Arc::new(UploadJournal::new(storage.path().join("journal.json")).unwrap());
+        let client = S3Client::new(&local_config(storage.path()))
+            .await
+            .unwrap()
+            .with_journal(journal.clone());
+
+        // First part was sent before the app was closed
+        let first = &content[..5 * MIB as usize];
+        let upload_id = client
+            .store
+            .initiate_multipart("a/resumed.bin", &PutOptions::default())
+            .await
+            .unwrap();
+        let etag = client
+            .store
+            .put_part(
+                "a/resumed.bin",
+                &upload_id,
+                1,
+                bytes_stream(first.to_vec()),
+                first.len() as u64,
+                &base64_md5(first),
+            )
+            .await
+            .unwrap();
+        journal.begin(JournalEntry {
+            upload_id,
+            s3_path: "a/resumed.bin".to_string(),
+            source_path: path.clone(),
+            file_size: metadata.len(),
+            modified: metadata.modified().unwrap().into(),
+            chunk_size: 5 * MIB,
+            started_at: Utc::now(),
+            sha256: None,
+            parts: vec![JournalPart {
+                part_number: 1,
+                etag,
+            }],
+        });
+
+        let object = client
+            .upload_file_auto_with_progress(&path, "a/new.bin", 5, 5, |_, _| {})
+            .await
+            .unwrap();
+
+        assert_eq!(object.key, "a/resumed.bin");
+        assert_eq!(object.sha256, format!("{:x}", Sha256::digest(&content)));
+        assert_eq!(
+            std::fs::read(storage.path().join("a/resumed.bin")).unwrap(),
+            content
+        );
+        assert!(journal.get_all().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_multipart_uploads_share_small_buffer_budget() {
+        let storage = tempfile::TempDir::new().unwrap();
+        let sources = tempfile::TempDir::new().unwrap();
+        // Three 5 MiB parts each, while the budget fits only one part at a time
+        let content: Vec<u8> = (0..11 * MIB).map(|i| (i % 251) as u8).collect();
+        let path = sources.path().join("video.bin");
+        std::fs::write(&path, &content).unwrap();
+
+        let mut config = local_config(storage.path());
+        config.advanced.buffer_memory_mb = 8;
+        let client = S3Client::new(&config).await.unwrap();
+
+        let uploads = async {
+            tokio::join!(
+                client.upload_file_auto_with_progress(&path, "a/file.bin", 5, 5, |_, _| {}),
+                client.upload_stream_multipart(
+                    content.as_slice(),
+                    "a/stream.bin",
+                    "application/octet-stream",
+                    content.len() as u64,
+                    5,
+                    |_| {},
+                ),
+            )
+        };
+        let (file, stream) = tokio::time::timeout(Duration::from_secs(30), uploads)
+            .await
+            .expect("uploads waited on buffer memory held by their own parts");
+
+        file.unwrap();
+        stream.unwrap();
+        for key in ["a/file.bin", "a/stream.bin"] {
+            assert_eq!(std::fs::read(storage.path().join(key)).unwrap(), content);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_links_follow_url_templates() {
+        let storage = tempfile::TempDir::new().unwrap();
+        let mut config = local_config(storage.path());
+        config.storage.local_base_url = Some("https://local.example.pl/".to_string());
+        let client = S3Client::new(&config).await.unwrap();
+        assert_eq!(
+            client.get_public_url("a/b c.txt"),
+            "https://local.example.pl/a/b%20c.txt"
+        );
+        assert_eq!(
+            client.get_direct_url("a/b c.txt"),
+            client.get_public_url("a/b c.txt")
+        );
+
+        config.storage.public_url = Some("https://files.example.pl/{key}".to_string());
+        config.storage.direct_url = Some("https://cdn.example.pl/raw".to_string());
+        let client = S3Client::new(&config).await.unwrap();
+        assert_eq!(
+            client.get_public_url("a/b c.txt"),
+            "https://files.example.pl/a/b%20c.txt"
         );
+        assert_eq!(
+            client.get_direct_url("a/b c.txt"),
+            "https://cdn.example.pl/raw/a/b%20c.txt"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_share_presigns_links() {
+        let config: Config = toml::from_str(
+            r#"
+[storage]
+backend = "s3"
+presigned_links = true
+
+[oracle]
+endpoint = "http://localhost:9000"
+bucket = "my-bucket"
+
+[app]
+auto_copy_link = false
+auto_start = false
+
+[advanced]
+parallel_uploads = 2
+multipart_threshold_mb = 5
+multipart_chunk_mb = 5
+"#,
+        )
+        .unwrap();
+        let client = S3Client::new_with_plaintext(&config, "access", "secret")
+            .await
+            .unwrap();
+        let object = UploadedObject {
+            key: "2026-02-06/a_0123456789abcdef.txt".to_string(),
+            url: client.get_public_url("2026-02-06/a_0123456789abcdef.txt"),
+            direct_url: client.get_direct_url("2026-02-06/a_0123456789abcdef.txt"),
+            expires_at: None,
+            par_id: None,
+            size: 5,
+            sha256: "abc".to_string(),
+            reused: false,
+        };
+
+        let shared = client.share(object, LinkExpiry::Hour).await.unwrap();
+        assert!(shared
+            .url
+            .starts_with("http://localhost:9000/my-bucket/2026-02-06/a_0123456789abcdef.txt?"));
+        assert!(shared.url.contains("X-Amz-Expires=3600"));
+        assert_eq!(shared.direct_url, shared.url);
+        assert_eq!(shared.par_id, None);
+        assert!(client.expiring_links() && !client.par_links());
+        let expires_in = shared.expires_at.unwrap() - Utc::now();
+        assert!(expires_in > chrono::Duration::minutes(59));
+        assert!(expires_in <= chrono::Duration::hours(1));
     }
 }
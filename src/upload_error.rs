@@ -0,0 +1,329 @@
+//! Typed upload failures, split into transient errors worth retrying and
+//! permanent ones that fail the upload immediately.
+
+use chrono::{DateTime, Utc};
+use s3::error::S3Error;
+use std::fmt;
+use std::time::Duration;
+
+/// Upper bound for any single retry delay, including server-requested ones.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+/// Longest excerpt of a response body kept in an error message.
+const MAX_MESSAGE_LEN: usize = 200;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum UploadError {
+    /// Connection failed, timed out or was reset
+    Network(String),
+    /// No connection to the endpoint could be made at all
+    Unreachable(String),
+    /// 5xx response
+    Server { status: u16, message: String },
+    /// 429 or SlowDown, with the delay the server asked for if it sent one
+    Throttled { retry_after: Option<Duration> },
+    /// Credentials or permissions rejected
+    Auth(String),
+    BucketNotFound,
+    /// The multipart session was aborted or expired on the server
+    SessionLost,
+    /// The stored object does not match the local file
+    Integrity(String),
+    /// Local file could not be read
+    LocalIo(String),
+    Cancelled,
+    /// Any other rejected request
+    Rejected { status: u16, message: String },
+    /// Failure without a recognizable cause
+    Other(String),
+}
+
+impl UploadError {
+    /// Classify an error response by status code and S3 error code in `body`.
+    pub fn from_response(status: u16, body: &str, retry_after: Option<Duration>) -> Self {
+        let code = error_code(body);
+        match (status, code.as_deref()) {
+            (429, _) | (_, Some("SlowDown")) => UploadError::Throttled { retry_after },
+            (_, Some("NoSuchUpload")) => UploadError::SessionLost,
+            (_, Some("NoSuchBucket")) => UploadError::BucketNotFound,
+            (401 | 403, code) => UploadError::Auth(code.unwrap_or("AccessDenied").to_string()),
+            (500..=599, code) => UploadError::Server {
+                status,
+                message: code.map_or_else(|| truncate(body), str::to_string),
+            },
+            (_, code) => UploadError::Rejected {
+                status,
+                message: code.map_or_else(|| truncate(body), str::to_string),
+            },
+        }
+    }
+
+    /// Find the cause of `error`. The outermost typed cause wins, so an I/O error
+    /// wrapped by the S3 or HTTP client counts as a network error, not a local one.
+    pub fn classify(error: &anyhow::Error) -> Self {
+        for cause in error.chain() {
+            if let Some(e) = cause.downcast_ref::<UploadError>() {
+                return e.clone();
+            }
+            if let Some(e) = cause.downcast_ref::<S3Error>() {
+                return Self::from_s3(e);
+            }
+            if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+                return Self::from_reqwest(e);
+            }
+            if cause.is::<tokio::time::error::Elapsed>() {
+                return UploadError::Network("request timed out".to_string());
+            }
+            if let Some(e) = cause.downcast_ref::<std::io::Error>() {
+                return UploadError::LocalIo(e.to_string());
+            }
+        }
+        UploadError::Other(error.to_string())
+    }
+
+    fn from_s3(error: &S3Error) -> Self {
+        match error {
+            S3Error::HttpFailWithBody(status, body) => Self::from_response(*status, body, None),
+            S3Error::Reqwest(e) => Self::from_reqwest(e),
+            S3Error::Io(e) => Self::from_io(e),
+            S3Error::Credentials(e) => UploadError::Auth(e.to_string()),
+            other => UploadError::Other(other.to_string()),
+        }
+    }
+
+    fn from_reqwest(error: &reqwest::Error) -> Self {
+        match error.status() {
+            Some(status) => Self::from_response(status.as_u16(), "", None),
+            None if error.is_connect() => UploadError::Unreachable(error.to_string()),
+            None => UploadError::Network(error.to_string()),
+        }
+    }
+
+    /// I/O error of a connection to the endpoint.
+    fn from_io(error: &std::io::Error) -> Self {
+        use std::io::ErrorKind;
+        match error.kind() {
+            ErrorKind::ConnectionRefused
+            | ErrorKind::NotConnected
+            | ErrorKind::AddrNotAvailable => UploadError::Unreachable(error.to_string()),
+            _ => UploadError::Network(error.to_string()),
+        }
+    }
+
+    /// Transient failures that another attempt can fix.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            UploadError::Network(_)
+                | UploadError::Unreachable(_)
+                | UploadError::Server { .. }
+                | UploadError::Throttled { .. }
+                | UploadError::SessionLost
+                | UploadError::Integrity(_)
+        )
+    }
+
+    /// Failures that may mean the endpoint cannot be reached, as opposed to
+    /// errors it reported.
+    pub fn is_connectivity(&self) -> bool {
+        matches!(self, UploadError::Network(_) | UploadError::Unreachable(_))
+    }
+
+    /// Delay before retry number `attempt` (1-based): the server's Retry-After when
+    /// throttled, otherwise jittered exponential backoff from `base`.
+    pub fn retry_delay(&self, attempt: u32, base: Duration) -> Duration {
+        match self {
+            UploadError::Throttled {
+                retry_after: Some(delay),
+            } => (*delay).min(MAX_RETRY_DELAY),
+            _ => backoff_with_jitter(attempt, base),
+        }
+    }
+}
+
+impl fmt::Display for UploadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UploadError::Network(msg) => write!(f, "Network error: {msg}"),
+            UploadError::Unreachable(msg) => write!(f, "Endpoint unreachable: {msg}"),
+            UploadError::Server { status, message } => {
+                write!(f, "Server error (HTTP {status}): {message}")
+            }
+            UploadError::Throttled { .. } => write!(f, "Server is throttling requests"),
+            UploadError::Auth(msg) => write!(f, "Access denied: {msg}"),
+            UploadError::BucketNotFound => write!(f, "Bucket does not exist"),
+            UploadError::SessionLost => write!(f, "Multipart upload session no longer exists"),
+            UploadError::Integrity(msg) => write!(f, "Integrity check failed: {msg}"),
+            UploadError::LocalIo(msg) => write!(f, "Cannot read file: {msg}"),
+            UploadError::Cancelled => write!(f, "Upload cancelled"),
+            UploadError::Rejected { status, message } => {
+                write!(f, "Request rejected (HTTP {status}): {message}")
+            }
+            UploadError::Other(msg) => write!(f, "{msg}"),
+        }
+    }
+}
+
+impl std::error::Error for UploadError {}
+
+/// Exponential backoff capped at `MAX_RETRY_DELAY`, randomized to the upper half of
+/// the window so parallel uploads failing together do not retry in lockstep.
+fn backoff_with_jitter(attempt: u32, base: Duration) -> Duration {
+    let exponential = base.saturating_mul(2_u32.saturating_pow(attempt.saturating_sub(1)));
+    let ceiling = exponential.min(MAX_RETRY_DELAY);
+    ceiling.mul_f64(rand::random_range(0.5..=1.0))
+}
+
+/// Parse a Retry-After header value, given either in seconds or as an HTTP date.
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    (date.with_timezone(&Utc) - Utc::now()).to_std().ok()
+}
+
+/// `<Code>` element of an S3 XML error body.
+fn error_code(body: &str) -> Option<String> {
+    let start = body.find("<Code>")? + "<Code>".len();
+    let end = start + body[start..].find("</Code>")?;
+    Some(body[start..end].to_string())
+}
+
+fn truncate(body: &str) -> String {
+    match body.char_indices().nth(MAX_MESSAGE_LEN) {
+        Some((idx, _)) => format!("{}...", &body[..idx]),
+        None => body.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_from_response_codes() {
+        let body = |code: &str| format!("<Error><Code>{code}</Code></Error>");
+
+        assert_eq!(
+            UploadError::from_response(503, &body("SlowDown"), None),
+            UploadError::Throttled { retry_after: None }
+        );
+        assert_eq!(
+            UploadError::from_response(404, &body("NoSuchUpload"), None),
+            UploadError::SessionLost
+        );
+        assert_eq!(
+            UploadError::from_response(404, &body("NoSuchBucket"), None),
+            UploadError::BucketNotFound
+        );
+        assert_eq!(
+            UploadError::from_response(403, &body("SignatureDoesNotMatch"), None),
+            UploadError::Auth("SignatureDoesNotMatch".to_string())
+        );
+        assert!(matches!(
+            UploadError::from_response(500, &body("InternalError"), None),
+            UploadError::Server { status: 500, .. }
+        ));
+        assert!(matches!(
+            UploadError::from_response(400, "bad request", None),
+            UploadError::Rejected { status: 400, .. }
+        ));
+    }
+
+    #[test]
+    fn test_retryable_classification() {
+        assert!(UploadError::Network("reset".to_string()).is_retryable());
+        assert!(UploadError::Throttled { retry_after: None }.is_retryable());
+        assert!(UploadError::from_response(502, "", None).is_retryable());
+
+        assert!(!UploadError::Auth("AccessDenied".to_string()).is_retryable());
+        assert!(!UploadError::BucketNotFound.is_retryable());
+        assert!(!UploadError::LocalIo("not found".to_string()).is_retryable());
+        assert!(!UploadError::Cancelled.is_retryable());
+    }
+
+    #[test]
+    fn test_classify_walks_error_chain() {
+        let s3 = anyhow::Error::new(S3Error::HttpFailWithBody(
+            403,
+            "<Error><Code>AccessDenied</Code></Error>".to_string(),
+        ))
+        .context("Failed to upload part 1");
+        assert_eq!(
+            UploadError::classify(&s3),
+            UploadError::Auth("AccessDenied".to_string())
+        );
+
+        let local = std::fs::File::open("definitely/missing/file.bin")
+            .context("Failed to open file")
+            .unwrap_err();
+        assert!(matches!(UploadError::classify(&local), UploadError::LocalIo(_)));
+
+        let typed = anyhow::Error::new(UploadError::Cancelled).context("Upload failed");
+        assert_eq!(UploadError::classify(&typed), UploadError::Cancelled);
+
+        let untyped = anyhow::anyhow!("Invalid filename");
+        assert!(!UploadError::classify(&untyped).is_retryable());
+    }
+
+    #[test]
+    fn test_s3_io_error_is_network() {
+        let err = anyhow::Error::new(S3Error::Io(std::io::Error::new(
+            std::io::ErrorKind::ConnectionReset,
+            "reset",
+        )));
+        assert!(matches!(UploadError::classify(&err), UploadError::Network(_)));
+
+        let refused = anyhow::Error::new(S3Error::Io(std::io::Error::new(
+            std::io::ErrorKind::ConnectionRefused,
+            "refused",
+        )));
+        let error = UploadError::classify(&refused);
+        assert!(matches!(error, UploadError::Unreachable(_)));
+        assert!(error.is_retryable() && error.is_connectivity());
+        assert!(!UploadError::from_response(503, "", None).is_connectivity());
+    }
+
+    #[test]
+    fn test_retry_delay_honors_retry_after() {
+        let throttled = UploadError::Throttled {
+            retry_after: Some(Duration::from_secs(7)),
+        };
+        assert_eq!(
+            throttled.retry_delay(1, Duration::from_secs(1)),
+            Duration::from_secs(7)
+        );
+
+        let capped = UploadError::Throttled {
+            retry_after: Some(Duration::from_secs(3600)),
+        };
+        assert_eq!(capped.retry_delay(1, Duration::from_secs(1)), MAX_RETRY_DELAY);
+    }
+
+    #[test]
+    fn test_backoff_with_jitter_bounds() {
+        let base = Duration::from_secs(2);
+        for attempt in 1..=4 {
+            let ceiling = base * 2_u32.pow(attempt - 1);
+            for _ in 0..20 {
+                let delay = backoff_with_jitter(attempt, base);
+                assert!(delay >= ceiling / 2 && delay <= ceiling);
+            }
+        }
+        assert!(backoff_with_jitter(30, base) <= MAX_RETRY_DELAY);
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_retry_after(" 5 "), Some(Duration::from_secs(5)));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), None);
+        assert_eq!(parse_retry_after("soon"), None);
+
+        let future = (Utc::now() + chrono::Duration::seconds(30)).to_rfc2822();
+        let delay = parse_retry_after(&future).unwrap();
+        assert!(delay <= Duration::from_secs(30) && delay >= Duration::from_secs(28));
+    }
+}
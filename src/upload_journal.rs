@@ -0,0 +1,176 @@
+//! Persistent journal of in-progress multipart uploads.
+//!
+//! Each confirmed part is written to disk so an upload interrupted by app exit,
+//! sleep or Windows shutdown can continue from the last confirmed part.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::json_store::JsonStore;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct JournalPart {
+    pub part_number: u32,
+    pub etag: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct JournalEntry {
+    pub upload_id: String,
+    pub s3_path: String,
+    pub source_path: PathBuf,
+    pub file_size: u64,
+    pub modified: DateTime<Utc>,
+    pub chunk_size: u64,
+    pub started_at: DateTime<Utc>,
+    /// SHA-256 of the source when it was known at initiate time and stored as metadata
+    #[serde(default)]
+    pub sha256: Option<String>,
+    #[serde(default)]
+    pub parts: Vec<JournalPart>,
+}
+
+impl JournalEntry {
+    /// Source file identity at the time the upload was started.
+    /// Resuming is only safe while size and mtime are unchanged.
+    pub fn matches_source(&self, file_size: u64, modified: DateTime<Utc>) -> bool {
+        self.file_size == file_size && self.modified == modified
+    }
+}
+
+pub struct UploadJournal {
+    store: JsonStore<JournalEntry>,
+}
+
+impl UploadJournal {
+    pub fn new(file_path: impl AsRef<Path>) -> Result<Self> {
+        Ok(UploadJournal {
+            store: JsonStore::open(file_path, "upload journal")?,
+        })
+    }
+
+    /// Register a freshly initiated multipart upload.
+    pub fn begin(&self, entry: JournalEntry) {
+        self.update(|entries| {
+            entries.retain(|e| e.source_path != entry.source_path);
+            entries.push(entry);
+        });
+    }
+
+    /// Record a part confirmed by the server.
+    pub fn record_part(&self, upload_id: &str, part_number: u32, etag: &str) {
+        self.update(|entries| {
+            if let Some(entry) = entries.iter_mut().find(|e| e.upload_id == upload_id) {
+                entry.parts.retain(|p| p.part_number != part_number);
+                entry.parts.push(JournalPart {
+                    part_number,
+                    etag: etag.to_string(),
+                });
+            }
+        });
+    }
+
+    pub fn remove(&self, upload_id: &str) {
+        self.update(|entries| entries.retain(|e| e.upload_id != upload_id));
+    }
+
+    pub fn find_by_source(&self, source_path: &Path) -> Option<JournalEntry> {
+        self.store
+            .read(|entries| {
+                entries
+                    .iter()
+                    .find(|e| e.source_path == source_path)
+                    .cloned()
+            })
+            .flatten()
+    }
+
+    pub fn get_all(&self) -> Vec<JournalEntry> {
+        self.store.get_all()
+    }
+
+    fn update(&self, f: impl FnOnce(&mut Vec<JournalEntry>)) {
+        self.store.update(f);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn sample_entry(upload_id: &str, source: &str) -> JournalEntry {
+        JournalEntry {
+            upload_id: upload_id.to_string(),
+            s3_path: "2026-02-06/video_0123456789abcdef.mp4".to_string(),
+            source_path: PathBuf::from(source),
+            file_size: 15 * 1024 * 1024,
+            modified: Utc::now(),
+            chunk_size: 5 * 1024 * 1024,
+            started_at: Utc::now(),
+            sha256: None,
+            parts: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_record_parts_persist() {
+        let temp_dir = TempDir::new().unwrap();
+        let journal_path = temp_dir.path().join("upload_journal.json");
+
+        {
+            let journal = UploadJournal::new(&journal_path).unwrap();
+            journal.begin(sample_entry("upload-1", "C:/video.mp4"));
+            journal.record_part("upload-1", 1, "\"etag-1\"");
+            journal.record_part("upload-1", 2, "\"etag-2\"");
+        }
+        assert!(!temp_dir.path().join("upload_journal.json.tmp").exists());
+
+        let journal = UploadJournal::new(&journal_path).unwrap();
+        let entry = journal.find_by_source(Path::new("C:/video.mp4")).unwrap();
+        assert_eq!(entry.upload_id, "upload-1");
+        assert_eq!(entry.parts.len(), 2);
+        assert_eq!(entry.parts[1].etag, "\"etag-2\"");
+    }
+
+    #[test]
+    fn test_begin_replaces_entry_for_same_source() {
+        let temp_dir = TempDir::new().unwrap();
+        let journal = UploadJournal::new(temp_dir.path().join("upload_journal.json")).unwrap();
+
+        journal.begin(sample_entry("upload-1", "C:/video.mp4"));
+        journal.begin(sample_entry("upload-2", "C:/video.mp4"));
+
+        let entries = journal.get_all();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].upload_id, "upload-2");
+    }
+
+    #[test]
+    fn test_remove_entry() {
+        let temp_dir = TempDir::new().unwrap();
+        let journal = UploadJournal::new(temp_dir.path().join("upload_journal.json")).unwrap();
+
+        journal.begin(sample_entry("upload-1", "C:/a.mp4"));
+        journal.begin(sample_entry("upload-2", "C:/b.mp4"));
+        journal.remove("upload-1");
+
+        let entries = journal.get_all();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].upload_id, "upload-2");
+    }
+
+    #[test]
+    fn test_matches_source_detects_changes() {
+        let entry = sample_entry("upload-1", "C:/video.mp4");
+
+        assert!(entry.matches_source(entry.file_size, entry.modified));
+        assert!(!entry.matches_source(entry.file_size + 1, entry.modified));
+        assert!(!entry.matches_source(
+            entry.file_size,
+            entry.modified + chrono::Duration::seconds(1)
+        ));
+    }
+}
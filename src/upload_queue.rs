@@ -0,0 +1,185 @@
+//! Persistent queue of dropped files that have not finished uploading.
+//!
+//! Entries are written when files are queued and removed once they upload or are
+//! cancelled, so files left behind by a crash, quit or reboot can be offered again.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::json_store::JsonStore;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum QueueState {
+    /// Queued or in progress when the app stopped
+    Pending,
+    /// Gave up after retries
+    Failed,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct QueueEntry {
+    pub file_id: String,
+    pub source_path: PathBuf,
+    /// Name reported in progress and history
+    pub filename: String,
+    pub s3_path: String,
+    pub file_size: u64,
+    pub modified: DateTime<Utc>,
+    pub queued_at: DateTime<Utc>,
+    pub state: QueueState,
+    #[serde(default)]
+    pub error: Option<String>,
+    /// Planned start of a deferred upload that has not started yet
+    #[serde(default)]
+    pub start_at: Option<DateTime<Utc>>,
+}
+
+pub struct UploadQueue {
+    store: JsonStore<QueueEntry>,
+}
+
+impl UploadQueue {
+    pub fn new(file_path: impl AsRef<Path>) -> Result<Self> {
+        Ok(UploadQueue {
+            store: JsonStore::open(file_path, "upload queue")?,
+        })
+    }
+
+    /// Record newly queued files in one write.
+    pub fn add_all(&self, new_entries: Vec<QueueEntry>) {
+        if new_entries.is_empty() {
+            return;
+        }
+        self.update(|entries| {
+            entries.retain(|e| !new_entries.iter().any(|n| n.file_id == e.file_id));
+            entries.extend(new_entries);
+        });
+    }
+
+    pub fn mark_failed(&self, file_id: &str, error: &str) {
+        self.update(|entries| {
+            if let Some(entry) = entries.iter_mut().find(|e| e.file_id == file_id) {
+                entry.state = QueueState::Failed;
+                entry.error = Some(error.to_string());
+            }
+        });
+    }
+
+    /// Forget the planned start once a deferred upload begins.
+    pub fn mark_started(&self, file_id: &str) {
+        self.update(|entries| {
+            if let Some(entry) = entries.iter_mut().find(|e| e.file_id == file_id) {
+                entry.start_at = None;
+            }
+        });
+    }
+
+    pub fn remove(&self, file_id: &str) {
+        self.update(|entries| entries.retain(|e| e.file_id != file_id));
+    }
+
+    pub fn get_all(&self) -> Vec<QueueEntry> {
+        self.store.get_all()
+    }
+
+    fn update(&self, f: impl FnOnce(&mut Vec<QueueEntry>)) {
+        self.store.update(f);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn sample_entry(file_id: &str) -> QueueEntry {
+        QueueEntry {
+            file_id: file_id.to_string(),
+            source_path: PathBuf::from(format!("C:/{file_id}.pdf")),
+            filename: format!("{file_id}.pdf"),
+            s3_path: format!("2026-02-06/{file_id}_0123456789abcdef.pdf"),
+            file_size: 1024,
+            modified: Utc::now(),
+            queued_at: Utc::now(),
+            state: QueueState::Pending,
+            error: None,
+            start_at: None,
+        }
+    }
+
+    #[test]
+    fn test_entries_persist_across_reload() {
+        let temp_dir = TempDir::new().unwrap();
+        let queue_path = temp_dir.path().join("upload_queue.json");
+
+        {
+            let queue = UploadQueue::new(&queue_path).unwrap();
+            queue.add_all(vec![sample_entry("a"), sample_entry("b"), sample_entry("c")]);
+            queue.mark_failed("b", "Access denied: AccessDenied");
+            queue.remove("c");
+        }
+
+        let queue = UploadQueue::new(&queue_path).unwrap();
+        let entries = queue.get_all();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].state, QueueState::Pending);
+        assert_eq!(entries[1].state, QueueState::Failed);
+        assert_eq!(entries[1].error.as_deref(), Some("Access denied: AccessDenied"));
+    }
+
+    #[test]
+    fn test_add_all_replaces_same_file_id() {
+        let temp_dir = TempDir::new().unwrap();
+        let queue = UploadQueue::new(temp_dir.path().join("upload_queue.json")).unwrap();
+
+        queue.add_all(vec![sample_entry("a"), sample_entry("b")]);
+        queue.mark_failed("a", "Network error: reset");
+        queue.add_all(vec![sample_entry("a")]);
+
+        let entries = queue.get_all();
+        assert_eq!(entries.len(), 2);
+        assert!(entries
+            .iter()
+            .all(|e| e.state == QueueState::Pending && e.error.is_none()));
+    }
+
+    #[test]
+    fn test_deferred_start_persists_until_started() {
+        let temp_dir = TempDir::new().unwrap();
+        let queue_path = temp_dir.path().join("upload_queue.json");
+        let start_at = Utc::now() + chrono::Duration::hours(8);
+
+        {
+            let queue = UploadQueue::new(&queue_path).unwrap();
+            queue.add_all(vec![
+                QueueEntry {
+                    start_at: Some(start_at),
+                    ..sample_entry("tonight")
+                },
+                QueueEntry {
+                    start_at: Some(start_at),
+                    ..sample_entry("started")
+                },
+            ]);
+            queue.mark_started("started");
+        }
+
+        let entries = UploadQueue::new(&queue_path).unwrap().get_all();
+        assert_eq!(entries[0].start_at, Some(start_at));
+        assert_eq!(entries[1].start_at, None);
+    }
+
+    #[test]
+    fn test_corrupted_file_starts_empty() {
+        let temp_dir = TempDir::new().unwrap();
+        let queue_path = temp_dir.path().join("upload_queue.json");
+        fs::write(&queue_path, "not json").unwrap();
+
+        let queue = UploadQueue::new(&queue_path).unwrap();
+        assert!(queue.get_all().is_empty());
+    }
+}